@@ -0,0 +1,26 @@
+// Text cleanup shared by the word counting binaries
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// Taken straight from chatgpt
+pub fn clean_text(input: &str) -> Vec<String> {
+    static PUNCTUATION: OnceLock<Regex> = OnceLock::new();
+    let re = PUNCTUATION.get_or_init(|| Regex::new(r"[^\w\s]").expect("Failed to compile regex"));
+    let mut results = Vec::new();
+
+    // Iterate over lines or chunks of text
+    for line in input.lines() {
+        // Remove punctuation
+        let clean_line = re.replace_all(line, "");
+        // Split the line into words and remove empty entries
+        results.extend(
+            clean_line
+                .split_whitespace()
+                .map(|word| word.to_lowercase()), // Convert to lowercase to standardize
+        );
+    }
+
+    results
+}
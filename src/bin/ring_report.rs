@@ -0,0 +1,74 @@
+use murmur_hash_rust::HashRing;
+use std::{env, process};
+
+const SEED: u32 = 50;
+
+// usage: ring_report [members] [virtual nodes per member] [keys]
+fn arg(n: usize, default: usize) -> usize {
+    match env::args().nth(n).map(|a| a.parse()) {
+        None => default,
+        Some(Ok(value)) => value,
+        Some(Err(_)) => {
+            eprintln!("usage: ring_report [members] [vnodes] [keys]");
+            process::exit(2);
+        }
+    }
+}
+
+fn routes(ring: &HashRing<String>, keys: usize) -> Vec<String> {
+    (0..keys as u64)
+        .map(|k| ring.route(&k).expect("ring has members").clone())
+        .collect()
+}
+
+fn moved(before: &[String], after: &[String]) -> usize {
+    before.iter().zip(after).filter(|(a, b)| a != b).count()
+}
+
+fn main() {
+    let members = arg(1, 10).max(1);
+    let vnodes = arg(2, 100);
+    let keys = arg(3, 100000);
+
+    let mut ring = HashRing::new(vnodes, SEED);
+    for i in 0..members {
+        ring.add(format!("node-{i}"));
+    }
+    let before = routes(&ring, keys);
+
+    // how evenly the keys spread
+    let ideal = keys as f64 / members as f64;
+    let mut spread = 0.0;
+    println!("{keys} keys on {members} members with {vnodes} virtual nodes each");
+    for node in ring.members() {
+        let count = before.iter().filter(|n| *n == node).count();
+        let off = (count as f64 - ideal) / ideal * 100.0;
+        spread += (count as f64 - ideal).powi(2);
+        println!("{node} => {count} ({off:+.1}%)");
+    }
+    let stddev = (spread / members as f64).sqrt();
+    println!(
+        "std dev {stddev:.1} keys, {:.1}% of ideal",
+        stddev / ideal * 100.0
+    );
+
+    // how many keys move when a member joins, then when one leaves
+    let joining = format!("node-{members}");
+    ring.add(joining.clone());
+    let joined = routes(&ring, keys);
+    println!(
+        "adding {joining} moved {} keys, ideal {}",
+        moved(&before, &joined),
+        keys / (members + 1)
+    );
+
+    ring.remove(&joining);
+    let leaving = String::from("node-0");
+    ring.remove(&leaving);
+    let left = routes(&ring, keys);
+    println!(
+        "removing {leaving} moved {} keys, ideal {}",
+        moved(&before, &left),
+        keys / members
+    );
+}
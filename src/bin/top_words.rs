@@ -0,0 +1,51 @@
+use murmur_hash_rust::TopK;
+use std::{
+    env,
+    io::{self, BufRead},
+    process,
+};
+
+mod common;
+
+use common::clean_text;
+
+const SEED: u32 = 50;
+
+// Like word_freq, but reads stdin a line at a time and only keeps the top
+// words, so it runs in fixed memory on input of any size. The counts are
+// estimates that may run a little high.
+fn main() {
+    let k = match env::args().nth(1).map(|n| n.parse::<usize>()) {
+        None => 10,
+        Some(Ok(k)) => k,
+        Some(Err(_)) => {
+            eprintln!("usage: top_words [number of words]");
+            process::exit(2);
+        }
+    };
+
+    let mut top: TopK<String> = TopK::new(k, SEED);
+    let mut reader = io::stdin().lock();
+    let mut line = Vec::new();
+    // bytes rather than lines, so invalid UTF-8 is replaced like word_freq
+    // does instead of ending the read
+    while let Ok(bytes_read) = reader.read_until(b'\n', &mut line) {
+        if bytes_read == 0 {
+            break;
+        }
+        for word in clean_text(&String::from_utf8_lossy(&line)) {
+            top.add(word);
+        }
+        line.clear();
+    }
+
+    for (word, count) in top.top() {
+        println!("{word} => ~{count}");
+    }
+    let sketch = top.sketch();
+    println!(
+        "{} words, counts may be up to {:.0} high",
+        sketch.total(),
+        sketch.error_bound()
+    );
+}
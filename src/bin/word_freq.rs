@@ -1,31 +1,27 @@
-use murmur_hash_rust::HashDict;
-use std::io::{self, BufReader, Read};
+use murmur_hash_rust::HashCounter;
+use std::{
+    env,
+    io::{self, BufReader, Read},
+    process,
+};
 
-use regex::Regex;
+mod common;
 
-/// Taken straight from chatgpt
-fn clean_text(input: &str) -> Vec<String> {
-    let re = Regex::new(r"[^\w\s]").expect("Failed to compile regex");
-    let mut results = Vec::new();
-
-    // Iterate over lines or chunks of text
-    for line in input.lines() {
-        // Remove punctuation
-        let clean_line = re.replace_all(line, "");
-        // Split the line into words and remove empty entries
-        results.extend(
-            clean_line
-                .split_whitespace()
-                .map(|word| word.to_lowercase()), // Convert to lowercase to standardize
-        );
-    }
-
-    results
-}
+use common::clean_text;
 
 const ARR_LEN: usize = 1000000;
 const SEED: u32 = 50;
 fn main() {
+    // `word_freq 10` prints the ten most common words, no argument prints
+    // every word sorted by key
+    let top = match env::args().nth(1).map(|n| n.parse::<usize>()) {
+        None => None,
+        Some(Ok(n)) => Some(n),
+        Some(Err(_)) => {
+            eprintln!("usage: word_freq [number of words]");
+            process::exit(2);
+        }
+    };
     let stdin = io::stdin();
     let mut reader = BufReader::new(stdin);
     let mut buffer = vec![0; 1024]; // Adjust buffer size as needed
@@ -40,16 +36,21 @@ fn main() {
         word_vec.extend(words);
     }
 
-    let mut dict: HashDict<String, usize> = HashDict::new(ARR_LEN, SEED);
-    for word in word_vec {
-        // dict.insert(word, 0);
-        if dict.get(&word).is_some() {
-            dict.get_mut(&word, |v| *v += 1);
-        } else {
-            dict.insert(word, 1);
+    let mut counter: HashCounter<String> = HashCounter::new(ARR_LEN, SEED);
+    counter.extend(word_vec);
+
+    match top {
+        Some(n) => {
+            for (k, v) in counter.most_common(n) {
+                println!("{k} => {v}");
+            }
+        }
+        None => {
+            let mut words: Vec<(&String, usize)> = counter.iter().collect();
+            words.sort_unstable();
+            for (k, v) in words {
+                println!("{k} => {v}");
+            }
         }
-    }
-    for (k, v) in dict.iter() {
-        println!("{k} => {v}");
     }
 }
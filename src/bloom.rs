@@ -0,0 +1,294 @@
+// Bloom filter on murmur3 double hashing
+//
+// The 128 bit murmur3 hash of an item is split into two 64 bit halves h1
+// and h2, and the k bit positions are h1 + i * h2 mod m for i in 0..k
+// (Kirsch & Mitzenmacher), so one hash call sets or tests all k bits.
+//
+// A filter answers "definitely not present" or "maybe present", which is
+// enough to skip a disk lookup for most missing keys. Sizing it from the
+// expected item count n and false positive rate p gives
+// m = -n ln p / (ln 2)^2 bits and k = m / n * ln 2 hashes.
+//
+// Byte layout from `to_bytes`, all integers little endian:
+//
+//   magic "MMBF" | version u16 | reserved u16 | seed u32 | hash count u32
+//   | bit count u64 | bits as u64 words | checksum u64
+//
+// The checksum is murmur3 over every byte in front of it.
+
+use std::{error::Error, f64::consts::LN_2, fmt};
+
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+
+use crate::{hash_anything, murmur::hash_mumur3};
+
+const MAGIC: [u8; 4] = *b"MMBF";
+/// Version written by `to_bytes`, older or newer filters are rejected
+pub const BLOOM_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 24;
+const CHECKSUM_SEED: u32 = 0x4d4d4246;
+
+#[derive(Debug)]
+pub enum BloomError {
+    /// The bytes do not start with the filter magic bytes
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The bytes end before the header says they would
+    Truncated,
+    /// The checksum does not match
+    ChecksumMismatch,
+    /// The checksum passed but the contents make no sense
+    Corrupt(String),
+    /// The filters differ in size, hash count or seed
+    Incompatible,
+}
+
+impl fmt::Display for BloomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BloomError::BadMagic => write!(f, "not a bloom filter"),
+            BloomError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported bloom filter version {v} (expected {BLOOM_VERSION})"
+            ),
+            BloomError::Truncated => write!(f, "bloom filter is truncated"),
+            BloomError::ChecksumMismatch => write!(f, "bloom filter checksum mismatch"),
+            BloomError::Corrupt(msg) => write!(f, "corrupt bloom filter: {msg}"),
+            BloomError::Incompatible => {
+                write!(f, "bloom filters differ in bit count, hash count or seed")
+            }
+        }
+    }
+}
+
+impl Error for BloomError {}
+
+/// # Bloom Filter
+///
+/// A set that may report false positives but never false negatives.
+///
+/// public functions:
+/// - new
+/// - with_params
+/// - insert
+/// - contains
+/// - clear
+/// - is_empty
+/// - union_with
+/// - intersect_with
+/// - estimated_count
+/// - false_positive_rate
+/// - bit_count
+/// - hash_count
+/// - seed
+/// - to_bytes
+/// - from_bytes
+#[derive(Clone, Debug, PartialEq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hashes: u32,
+    seed: u32,
+}
+
+impl BloomFilter {
+    /// Creates a filter that holds `expected` items with a false positive
+    /// rate of about `fp_rate`
+    ///
+    /// Panics if `fp_rate` isn't between 0 and 1
+    pub fn new(expected: usize, fp_rate: f64, seed: u32) -> Self {
+        assert!(
+            fp_rate > 0.0 && fp_rate < 1.0,
+            "false positive rate must be between 0 and 1, got {fp_rate}"
+        );
+        let n = expected.max(1) as f64;
+        let bits = (-n * fp_rate.ln() / (LN_2 * LN_2)).ceil();
+        let hashes = (bits / n * LN_2).round();
+        Self::with_params(bits as u64, hashes as u32, seed)
+    }
+
+    /// Creates a filter of `bits` bits setting `hashes` bits per item,
+    /// both at least 1
+    pub fn with_params(bits: u64, hashes: u32, seed: u32) -> Self {
+        let bit_count = bits.max(1);
+        BloomFilter {
+            bits: vec![0; bit_count.div_ceil(64) as usize],
+            bit_count,
+            hashes: hashes.max(1),
+            seed,
+        }
+    }
+
+    /// Adds `item`, returning false if it may have been there already
+    pub fn insert<T: Serialize + ?Sized>(&mut self, item: &T) -> bool {
+        let mut added = false;
+        for bit in self.positions(item) {
+            let (word, mask) = (bit / 64, 1u64 << (bit % 64));
+            added |= self.bits[word as usize] & mask == 0;
+            self.bits[word as usize] |= mask;
+        }
+        added
+    }
+
+    /// False if `item` was definitely never inserted
+    pub fn contains<T: Serialize + ?Sized>(&self, item: &T) -> bool {
+        self.positions(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1u64 << (bit % 64)) != 0)
+    }
+
+    /// Forgets every item, keeping the size
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|w| *w == 0)
+    }
+
+    /// Adds every item of `other`, which must have the same size, hash
+    /// count and seed
+    pub fn union_with(&mut self, other: &Self) -> Result<(), BloomError> {
+        self.check_compatible(other)?;
+        for (mine, theirs) in self.bits.iter_mut().zip(&other.bits) {
+            *mine |= theirs;
+        }
+        Ok(())
+    }
+
+    /// Keeps only the bits `other` has too. The result may report a few
+    /// more false positives than a filter built from the common items.
+    pub fn intersect_with(&mut self, other: &Self) -> Result<(), BloomError> {
+        self.check_compatible(other)?;
+        for (mine, theirs) in self.bits.iter_mut().zip(&other.bits) {
+            *mine &= theirs;
+        }
+        Ok(())
+    }
+
+    /// Estimates how many distinct items were inserted from the number of
+    /// set bits (Swamidass & Baldi)
+    pub fn estimated_count(&self) -> f64 {
+        let m = self.bit_count as f64;
+        let set = self.set_bits() as f64;
+        if set >= m {
+            return f64::INFINITY;
+        }
+        -m / self.hashes as f64 * (1.0 - set / m).ln()
+    }
+
+    /// Chance that `contains` says yes to an item that was never inserted,
+    /// given the bits set so far
+    pub fn false_positive_rate(&self) -> f64 {
+        (self.set_bits() as f64 / self.bit_count as f64).powi(self.hashes as i32)
+    }
+
+    pub fn bit_count(&self) -> u64 {
+        self.bit_count
+    }
+
+    /// Bits set per item
+    pub fn hash_count(&self) -> u32 {
+        self.hashes
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Encodes the filter in the layout described at the top of this file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; HEADER_LEN + self.bits.len() * 8 + 8];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        LittleEndian::write_u16(&mut bytes[4..6], BLOOM_VERSION);
+        LittleEndian::write_u32(&mut bytes[8..12], self.seed);
+        LittleEndian::write_u32(&mut bytes[12..16], self.hashes);
+        LittleEndian::write_u64(&mut bytes[16..24], self.bit_count);
+        let end = bytes.len() - 8;
+        LittleEndian::write_u64_into(&self.bits, &mut bytes[HEADER_LEN..end]);
+        let sum = checksum(&bytes[..end]);
+        LittleEndian::write_u64(&mut bytes[end..], sum);
+        bytes
+    }
+
+    /// Decodes a filter written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BloomError> {
+        if bytes.len() < 4 {
+            return Err(BloomError::Truncated);
+        }
+        if bytes[0..4] != MAGIC {
+            return Err(BloomError::BadMagic);
+        }
+        if bytes.len() < HEADER_LEN + 8 {
+            return Err(BloomError::Truncated);
+        }
+        let version = LittleEndian::read_u16(&bytes[4..6]);
+        if version != BLOOM_VERSION {
+            return Err(BloomError::UnsupportedVersion(version));
+        }
+        let bit_count = LittleEndian::read_u64(&bytes[16..24]);
+        let words = bit_count.div_ceil(64);
+        let expected = words
+            .checked_mul(8)
+            .and_then(|len| len.checked_add((HEADER_LEN + 8) as u64));
+        match expected {
+            Some(len) if (bytes.len() as u64) < len => return Err(BloomError::Truncated),
+            Some(len) if bytes.len() as u64 == len => {}
+            _ => {
+                return Err(BloomError::Corrupt(format!(
+                    "{} bytes for {bit_count} bits",
+                    bytes.len()
+                )))
+            }
+        }
+        let end = bytes.len() - 8;
+        if LittleEndian::read_u64(&bytes[end..]) != checksum(&bytes[..end]) {
+            return Err(BloomError::ChecksumMismatch);
+        }
+
+        let hashes = LittleEndian::read_u32(&bytes[12..16]);
+        if bit_count == 0 || hashes == 0 {
+            return Err(BloomError::Corrupt(format!(
+                "{bit_count} bits with {hashes} hashes"
+            )));
+        }
+        let mut bits = vec![0u64; words as usize];
+        LittleEndian::read_u64_into(&bytes[HEADER_LEN..end], &mut bits);
+        Ok(BloomFilter {
+            bits,
+            bit_count,
+            hashes,
+            seed: LittleEndian::read_u32(&bytes[8..12]),
+        })
+    }
+
+    // the k bit positions of `item`
+    fn positions<T: Serialize + ?Sized>(&self, item: &T) -> impl Iterator<Item = u64> {
+        let hash = hash_anything(item, self.seed).unwrap_or_default();
+        let h1 = hash as u64;
+        // odd, so the positions don't collapse onto h1 when h2 is 0
+        let h2 = (hash >> 64) as u64 | 1;
+        let m = self.bit_count;
+        (0..self.hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % m)
+    }
+
+    fn set_bits(&self) -> u64 {
+        self.bits.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    fn check_compatible(&self, other: &Self) -> Result<(), BloomError> {
+        if self.bit_count == other.bit_count
+            && self.hashes == other.hashes
+            && self.seed == other.seed
+        {
+            Ok(())
+        } else {
+            Err(BloomError::Incompatible)
+        }
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    hash_mumur3(bytes, CHECKSUM_SEED).unwrap_or_default() as u64
+}
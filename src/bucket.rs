@@ -0,0 +1,454 @@
+// Collision strategies for HashDict buckets
+//
+// A HashDict keeps the entries that hash to the same slot in a bucket, and
+// which kind of bucket is picked by its third type parameter:
+//
+//   RbTree        O(log n) in the bucket, but every bucket is a whole tree
+//   SmallBucket   inline for 0 or 1 entries, a plain vector past that
+//   HybridBucket  a SmallBucket that turns into an RbTree once it gets long,
+//                 the way Java 8's HashMap does it
+//
+// With a decent hash and load factor most buckets hold zero or one entry, so
+// the list-based buckets are usually the lighter choice.
+
+use std::{borrow::Borrow, fmt::Debug, iter, mem, slice};
+
+use crate::{RbRefIter, RbTree};
+
+/// A HybridBucket holding more entries than this turns into a tree
+pub const TREEIFY_THRESHOLD: usize = 8;
+/// A tree HybridBucket shrinking to this many entries turns back into a list
+pub const UNTREEIFY_THRESHOLD: usize = 6;
+
+/// Storage for the entries of one hash table slot
+pub trait Bucket<K: PartialOrd, V>: Default {
+    type Iter<'a>: Iterator<Item = (&'a K, &'a V)>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts a key-value pair, handing back the value it replaced
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized;
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized;
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized;
+
+    /// Takes out every entry `f` returns true for, in one pass over the
+    /// bucket
+    fn extract_if<F>(&mut self, f: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool;
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Most entries a lookup has to compare against, the tree height for
+    /// trees and the length for lists
+    fn height(&self) -> usize;
+
+    fn shrink_to_fit(&mut self) {}
+}
+
+// -------------------- RB TREE --------------------
+
+impl<K: PartialOrd + Debug, V: Debug> Bucket<K, V> for RbTree<K, V> {
+    type Iter<'a>
+        = RbRefIter<'a, K, V>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn len(&self) -> usize {
+        self.len() as usize
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.get_mut(&key) {
+            Some(old) => Some(mem::replace(old, value)),
+            None => {
+                RbTree::insert(self, key, value);
+                None
+            }
+        }
+    }
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.get_key_value(key)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.get_mut(key)
+    }
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.remove_entry(key)
+    }
+
+    fn extract_if<F>(&mut self, f: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.extract_if(f)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter_ref()
+    }
+
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.shrink_to_fit()
+    }
+}
+
+// -------------------- SMALL VECTOR --------------------
+
+/// # Small Bucket
+///
+/// Unsorted list of entries that stores a single entry inline and only
+/// allocates once a second one arrives.
+#[derive(Clone, Debug, Default)]
+pub enum SmallBucket<K, V> {
+    #[default]
+    Empty,
+    One((K, V)),
+    Many(Vec<(K, V)>),
+}
+
+pub type SmallBucketIter<'a, K, V> = iter::Map<slice::Iter<'a, (K, V)>, fn(&(K, V)) -> (&K, &V)>;
+
+impl<K, V> SmallBucket<K, V> {
+    fn entries(&self) -> &[(K, V)] {
+        match self {
+            SmallBucket::Empty => &[],
+            SmallBucket::One(entry) => slice::from_ref(entry),
+            SmallBucket::Many(entries) => entries,
+        }
+    }
+
+    fn entries_mut(&mut self) -> &mut [(K, V)] {
+        match self {
+            SmallBucket::Empty => &mut [],
+            SmallBucket::One(entry) => slice::from_mut(entry),
+            SmallBucket::Many(entries) => entries,
+        }
+    }
+
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.entries().iter().position(|(k, _)| k.borrow() == key)
+    }
+
+    // appends without checking for the key, callers make sure it's new
+    fn push(&mut self, entry: (K, V)) {
+        *self = match mem::take(self) {
+            SmallBucket::Empty => SmallBucket::One(entry),
+            SmallBucket::One(first) => SmallBucket::Many(vec![first, entry]),
+            SmallBucket::Many(mut entries) => {
+                entries.push(entry);
+                SmallBucket::Many(entries)
+            }
+        };
+    }
+
+    // the entries must have distinct keys
+    fn from_entries(mut entries: Vec<(K, V)>) -> Self {
+        match entries.len() {
+            0 => SmallBucket::Empty,
+            1 => SmallBucket::One(entries.pop().expect("one entry")),
+            _ => SmallBucket::Many(entries),
+        }
+    }
+
+    // hands out every entry, leaving the bucket empty
+    fn drain(&mut self) -> Vec<(K, V)> {
+        match mem::take(self) {
+            SmallBucket::Empty => Vec::new(),
+            SmallBucket::One(entry) => vec![entry],
+            SmallBucket::Many(entries) => entries,
+        }
+    }
+}
+
+impl<K: PartialOrd, V> Bucket<K, V> for SmallBucket<K, V> {
+    type Iter<'a>
+        = SmallBucketIter<'a, K, V>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.position(&key) {
+            Some(i) => Some(mem::replace(&mut self.entries_mut()[i].1, value)),
+            None => {
+                self.push((key, value));
+                None
+            }
+        }
+    }
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let (k, v) = &self.entries()[self.position(key)?];
+        Some((k, v))
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let i = self.position(key)?;
+        Some(&mut self.entries_mut()[i].1)
+    }
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let i = self.position(key)?;
+        match mem::take(self) {
+            SmallBucket::Empty => unreachable!("found a key in an empty bucket"),
+            SmallBucket::One(entry) => Some(entry),
+            SmallBucket::Many(mut entries) => {
+                let entry = entries.swap_remove(i);
+                *self = SmallBucket::from_entries(entries);
+                Some(entry)
+            }
+        }
+    }
+
+    fn extract_if<F>(&mut self, mut f: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let mut kept = Vec::new();
+        let mut extracted = Vec::new();
+        for (k, mut v) in self.drain() {
+            if f(&k, &mut v) {
+                extracted.push((k, v));
+            } else {
+                kept.push((k, v));
+            }
+        }
+        *self = SmallBucket::from_entries(kept);
+        extracted
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.entries().iter().map(|(k, v)| (k, v))
+    }
+
+    fn height(&self) -> usize {
+        self.len()
+    }
+
+    fn shrink_to_fit(&mut self) {
+        if let SmallBucket::Many(entries) = self {
+            entries.shrink_to_fit();
+        }
+    }
+}
+
+// -------------------- HYBRID --------------------
+
+/// # Hybrid Bucket
+///
+/// A `SmallBucket` while short, an `RbTree` once it holds more than
+/// `TREEIFY_THRESHOLD` entries, and a list again at `UNTREEIFY_THRESHOLD`.
+/// The gap between the two keeps a bucket hovering around the limit from
+/// converting on every insert and delete.
+#[derive(Clone, Debug)]
+pub enum HybridBucket<K: PartialOrd, V: Debug> {
+    List(SmallBucket<K, V>),
+    Tree(RbTree<K, V>),
+}
+
+impl<K: PartialOrd, V: Debug> Default for HybridBucket<K, V> {
+    fn default() -> Self {
+        HybridBucket::List(SmallBucket::Empty)
+    }
+}
+
+impl<K: PartialOrd + Debug, V: Debug> HybridBucket<K, V> {
+    pub fn is_tree(&self) -> bool {
+        matches!(self, HybridBucket::Tree(_))
+    }
+
+    fn untreeify_if_short(&mut self) {
+        if let HybridBucket::Tree(tree) = self {
+            if tree.len() as usize <= UNTREEIFY_THRESHOLD {
+                let entries = mem::take(tree).into_sorted_vec();
+                *self = HybridBucket::List(SmallBucket::from_entries(entries));
+            }
+        }
+    }
+}
+
+pub enum HybridBucketIter<'a, K: PartialOrd, V: Debug> {
+    List(SmallBucketIter<'a, K, V>),
+    Tree(RbRefIter<'a, K, V>),
+}
+
+impl<'a, K: PartialOrd, V: Debug> Iterator for HybridBucketIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            HybridBucketIter::List(iter) => iter.next(),
+            HybridBucketIter::Tree(iter) => iter.next(),
+        }
+    }
+}
+
+impl<K: PartialOrd + Debug, V: Debug> Bucket<K, V> for HybridBucket<K, V> {
+    type Iter<'a>
+        = HybridBucketIter<'a, K, V>
+    where
+        Self: 'a,
+        K: 'a,
+        V: 'a;
+
+    fn len(&self) -> usize {
+        match self {
+            HybridBucket::List(list) => list.len(),
+            HybridBucket::Tree(tree) => tree.len() as usize,
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = match self {
+            HybridBucket::List(list) => list.insert(key, value),
+            HybridBucket::Tree(tree) => Bucket::insert(tree, key, value),
+        };
+
+        if let HybridBucket::List(list) = self {
+            if list.len() > TREEIFY_THRESHOLD {
+                let mut tree = RbTree::new();
+                for (k, v) in list.drain() {
+                    tree.insert(k, v);
+                }
+                *self = HybridBucket::Tree(tree);
+            }
+        }
+        old
+    }
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        match self {
+            HybridBucket::List(list) => list.get_key_value(key),
+            HybridBucket::Tree(tree) => tree.get_key_value(key),
+        }
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        match self {
+            HybridBucket::List(list) => list.get_mut(key),
+            HybridBucket::Tree(tree) => tree.get_mut(key),
+        }
+    }
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let entry = match self {
+            HybridBucket::List(list) => list.remove_entry(key),
+            HybridBucket::Tree(tree) => tree.remove_entry(key),
+        };
+        self.untreeify_if_short();
+        entry
+    }
+
+    fn extract_if<F>(&mut self, f: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let extracted = match self {
+            HybridBucket::List(list) => list.extract_if(f),
+            HybridBucket::Tree(tree) => tree.extract_if(f),
+        };
+        self.untreeify_if_short();
+        extracted
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        match self {
+            HybridBucket::List(list) => HybridBucketIter::List(list.iter()),
+            HybridBucket::Tree(tree) => HybridBucketIter::Tree(tree.iter_ref()),
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            HybridBucket::List(list) => list.height(),
+            HybridBucket::Tree(tree) => tree.height(),
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        match self {
+            HybridBucket::List(list) => list.shrink_to_fit(),
+            HybridBucket::Tree(tree) => tree.shrink_to_fit(),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// Thread-safe HashDict, split into independently locked shards
+//
+// Every key is routed to one shard by its murmur3 hash and each shard is a
+// plain HashDict behind its own read-write lock, so readers never wait on
+// each other and writers only wait on writers of the same shard.
+
+use std::{
+    borrow::Borrow,
+    fmt::Debug,
+    mem,
+    ops::{Deref, DerefMut},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use serde::Serialize;
+
+use crate::{hash_anything, HashDict};
+
+/// # Concurrent Hash Dictionary
+///
+/// A `HashDict` that can be shared between threads, every method takes `&self`.
+///
+/// public functions:
+/// - new
+/// - insert
+/// - get
+/// - remove
+/// - entry
+/// - contains_key
+/// - len
+pub struct ConcurrentHashDict<K, V>
+where
+    K: PartialOrd + Serialize,
+    V: Debug,
+{
+    seed: u32,
+    shards: Vec<RwLock<HashDict<K, V>>>,
+}
+
+impl<K, V> ConcurrentHashDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    /// Creates a table of `shards` locks, each guarding a HashDict of
+    /// `buckets` buckets
+    pub fn new(shards: usize, buckets: usize, seed: u32) -> Self {
+        assert!(shards > 0, "ConcurrentHashDict needs at least one shard");
+        ConcurrentHashDict {
+            seed,
+            shards: (0..shards)
+                .map(|_| RwLock::new(HashDict::new(buckets, seed)))
+                .collect(),
+        }
+    }
+
+    /// Inserts a key-value pair, handing back the value it replaced
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut shard = self.write(&key);
+        match shard.get_mut(&key) {
+            Some(old) => Some(mem::replace(old, value)),
+            None => {
+                shard.insert(key, value);
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        V: Clone,
+    {
+        self.read(key).get(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.read(key).contains_key(key)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.write(key).pop(key)
+    }
+
+    /// Locks the shard holding `key` for a read-modify-write.
+    ///
+    /// The shard stays locked until the entry, or the value guard it
+    /// turns into, is dropped. Other keys of the same shard have to wait
+    /// until then, so don't hold on to it longer than needed.
+    pub fn entry(&self, key: K) -> ShardEntry<'_, K, V> {
+        let shard = self.write(&key);
+        ShardEntry { shard, key }
+    }
+
+    /// Number of entries across all shards. The shards are counted one at
+    /// a time, so the total can be stale if other threads are writing.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.read().expect("shard lock poisoned").len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard_index<Q>(&self, key: &Q) -> usize
+    where
+        Q: Serialize + ?Sized,
+    {
+        // The shard dicts use the low bits of the same hash for their
+        // buckets, routing by the high half keeps the two choices
+        // independent. Otherwise every key of a shard would fall into the
+        // same fraction of its buckets.
+        hash_anything(key, self.seed)
+            .map(|hash| ((hash >> 64) % self.shards.len() as u128) as usize)
+            .unwrap_or(0)
+    }
+
+    fn read<Q>(&self, key: &Q) -> RwLockReadGuard<'_, HashDict<K, V>>
+    where
+        Q: Serialize + ?Sized,
+    {
+        self.shards[self.shard_index(key)]
+            .read()
+            .expect("shard lock poisoned")
+    }
+
+    fn write<Q>(&self, key: &Q) -> RwLockWriteGuard<'_, HashDict<K, V>>
+    where
+        Q: Serialize + ?Sized,
+    {
+        self.shards[self.shard_index(key)]
+            .write()
+            .expect("shard lock poisoned")
+    }
+}
+
+/// A locked shard and a key in it, see `ConcurrentHashDict::entry`
+pub struct ShardEntry<'a, K, V>
+where
+    K: PartialOrd + Serialize,
+    V: Debug,
+{
+    shard: RwLockWriteGuard<'a, HashDict<K, V>>,
+    key: K,
+}
+
+impl<'a, K, V> ShardEntry<'a, K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> Option<&V> {
+        self.shard.get_ref(&self.key)
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        self.shard.get_mut(&self.key)
+    }
+
+    /// Runs `f` on the value if the key is present
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Some(v) = self.get_mut() {
+            f(v);
+        }
+        self
+    }
+
+    /// Removes the key, keeping the shard locked
+    pub fn remove(&mut self) -> Option<V> {
+        self.shard.pop(&self.key)
+    }
+
+    /// Inserts `default` if the key is missing and hands back a guard to
+    /// the value
+    pub fn or_insert(self, default: V) -> ShardGuard<'a, K, V>
+    where
+        K: Clone,
+    {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> ShardGuard<'a, K, V>
+    where
+        K: Clone,
+    {
+        let ShardEntry { mut shard, key } = self;
+        if !shard.contains_key(&key) {
+            shard.insert(key.clone(), f());
+        }
+        ShardGuard { shard, key }
+    }
+}
+
+/// Keeps a shard locked while its value is borrowed, derefs to the value
+pub struct ShardGuard<'a, K, V>
+where
+    K: PartialOrd + Serialize,
+    V: Debug,
+{
+    shard: RwLockWriteGuard<'a, HashDict<K, V>>,
+    key: K,
+}
+
+impl<K, V> Deref for ShardGuard<'_, K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.shard
+            .get_ref(&self.key)
+            .expect("entry removed while guarded")
+    }
+}
+
+impl<K, V> DerefMut for ShardGuard<'_, K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    fn deref_mut(&mut self) -> &mut V {
+        self.shard
+            .get_mut(&self.key)
+            .expect("entry removed while guarded")
+    }
+}
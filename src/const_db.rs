@@ -0,0 +1,359 @@
+// Read-only constant hash database, in the spirit of djb's cdb
+//
+// A HashDict is written out once into a file laid out as a hash table, and
+// readers mmap that file and look keys up in place. Nothing is deserialized
+// up front, so opening is O(1) and processes on the same host share the
+// pages through the OS page cache.
+//
+// Layout, all integers little endian:
+//
+//   header     magic "MMCD" | version u16 | reserved u16 | seed u32
+//              | slot count u64 | entry count u64 | header checksum u64
+//   directory  (records offset u64 | record count u32 | reserved u32) per slot
+//   records    (hash u64 | key length u32 | value length u32 | key | value)*
+//
+// A key lives in slot `murmur3(key) % slot count`, and all records of a slot
+// are stored back to back. Keys and values are bincode encoded, the same
+// encoding HashDict hashes, so borrowed key forms work here too.
+
+use std::{
+    borrow::Borrow,
+    error::Error,
+    fmt::{self, Debug},
+    fs::{File, OpenOptions},
+    io,
+    marker::PhantomData,
+    path::Path,
+};
+
+use byteorder::{ByteOrder, LittleEndian};
+use memmap2::{Mmap, MmapMut};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{murmur::hash_mumur3, snapshot::write_atomically, Bucket, HashDict};
+
+const MAGIC: [u8; 4] = *b"MMCD";
+/// Version written by `ConstDb::write`
+pub const CONST_DB_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 36;
+const SLOT_LEN: usize = 16;
+const RECORD_HEADER_LEN: usize = 16;
+const CHECKSUM_SEED: u32 = 0x4d4d4344;
+
+#[derive(Debug)]
+pub enum ConstDbError {
+    Io(io::Error),
+    /// The file does not start with the database magic bytes
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The file is shorter than its header says
+    Truncated,
+    /// The header checksum does not match
+    CorruptHeader,
+    /// A slot or record points outside of the file
+    Corrupt(String),
+    Encode(bincode::Error),
+    Decode(bincode::Error),
+}
+
+impl fmt::Display for ConstDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstDbError::Io(e) => write!(f, "const db i/o error: {e}"),
+            ConstDbError::BadMagic => write!(f, "not a const db file"),
+            ConstDbError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported const db version {v} (expected {CONST_DB_VERSION})"
+            ),
+            ConstDbError::Truncated => write!(f, "const db file is truncated"),
+            ConstDbError::CorruptHeader => write!(f, "const db header checksum mismatch"),
+            ConstDbError::Corrupt(msg) => write!(f, "corrupt const db: {msg}"),
+            ConstDbError::Encode(e) => write!(f, "failed to encode entry: {e}"),
+            ConstDbError::Decode(e) => write!(f, "failed to decode entry: {e}"),
+        }
+    }
+}
+
+impl Error for ConstDbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConstDbError::Io(e) => Some(e),
+            ConstDbError::Encode(e) | ConstDbError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConstDbError {
+    fn from(e: io::Error) -> Self {
+        ConstDbError::Io(e)
+    }
+}
+
+fn header_checksum(header: &[u8]) -> Result<u64, ConstDbError> {
+    Ok(hash_mumur3(header, CHECKSUM_SEED)? as u64)
+}
+
+// a key or value length as stored in a record, which has 4 bytes for it
+fn record_len(len: u64) -> Result<u32, ConstDbError> {
+    u32::try_from(len).map_err(|_| {
+        ConstDbError::Encode(Box::new(bincode::ErrorKind::Custom(format!(
+            "{len} bytes don't fit a record"
+        ))))
+    })
+}
+
+/// # Constant Hash Database
+///
+/// An immutable, memory-mapped hash file built from a `HashDict`.
+///
+/// public functions:
+/// - write
+/// - open
+/// - get
+/// - get_bytes
+/// - contains_key
+/// - len
+pub struct ConstDb<K, V> {
+    map: Mmap,
+    seed: u32,
+    slots: u64,
+    entries: u64,
+    // fn() keeps the db Send + Sync no matter what K and V are
+    _types: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> ConstDb<K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Serialize + Debug,
+{
+    /// Writes `dict` out as a constant database at `path`.
+    ///
+    /// The file gets one slot per entry and keeps the dict's seed. It is
+    /// sized up front and filled through a writable mapping, so no copy of
+    /// the entries is built in memory. Like `HashDict::save_to` it is written
+    /// next to `path` and renamed into place, and the temporary file is
+    /// removed again if anything fails.
+    pub fn write<B, P>(dict: &HashDict<K, V, B>, path: P) -> Result<(), ConstDbError>
+    where
+        B: Bucket<K, V>,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let seed = dict.seed();
+        let slots = dict.len().max(1);
+
+        // pass 1: how many records land in each slot and how many bytes they take
+        let mut counts = vec![0u32; slots];
+        let mut sizes = vec![0u64; slots];
+        dict.try_for_each(|k, v| {
+            let key = bincode::serialize(k).map_err(ConstDbError::Encode)?;
+            let val_len = bincode::serialized_size(v).map_err(ConstDbError::Encode)?;
+            // both lengths have to fit their u32 record fields
+            record_len(key.len() as u64)?;
+            record_len(val_len)?;
+            let slot = (hash_mumur3(&key, seed)? % slots as u128) as usize;
+            counts[slot] += 1;
+            sizes[slot] += (RECORD_HEADER_LEN + key.len()) as u64 + val_len;
+            Ok::<(), ConstDbError>(())
+        })?;
+
+        let dir_len = (slots * SLOT_LEN) as u64;
+        let total_len = HEADER_LEN as u64 + dir_len + sizes.iter().sum::<u64>();
+
+        write_atomically(path, |tmp_path| {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(tmp_path)?;
+            file.set_len(total_len)?;
+            // SAFETY: the temp file was just created by us and nothing else maps it
+            let mut map = unsafe { MmapMut::map_mut(&file)? };
+
+            map[0..4].copy_from_slice(&MAGIC);
+            LittleEndian::write_u16(&mut map[4..6], CONST_DB_VERSION);
+            LittleEndian::write_u16(&mut map[6..8], 0);
+            LittleEndian::write_u32(&mut map[8..12], seed);
+            LittleEndian::write_u64(&mut map[12..20], slots as u64);
+            LittleEndian::write_u64(&mut map[20..28], dict.len() as u64);
+            let checksum = header_checksum(&map[0..28])?;
+            LittleEndian::write_u64(&mut map[28..36], checksum);
+
+            // directory, remembering where the next record of each slot goes
+            let mut cursors = Vec::with_capacity(slots);
+            let mut offset = HEADER_LEN as u64 + dir_len;
+            for (slot, (&count, &size)) in counts.iter().zip(sizes.iter()).enumerate() {
+                let at = HEADER_LEN + slot * SLOT_LEN;
+                LittleEndian::write_u64(&mut map[at..at + 8], offset);
+                LittleEndian::write_u32(&mut map[at + 8..at + 12], count);
+                cursors.push(offset as usize);
+                offset += size;
+            }
+
+            // pass 2: encode every record straight into its place in the file
+            dict.try_for_each(|k, v| {
+                let key = bincode::serialize(k).map_err(ConstDbError::Encode)?;
+                let hash = hash_mumur3(&key, seed)?;
+                let slot = (hash % slots as u128) as usize;
+                let at = cursors[slot];
+                let key_at = at + RECORD_HEADER_LEN;
+                let val_at = key_at + key.len();
+
+                // the low half of the hash lets lookups skip most key compares
+                LittleEndian::write_u64(&mut map[at..at + 8], hash as u64);
+                LittleEndian::write_u32(&mut map[at + 8..at + 12], record_len(key.len() as u64)?);
+                map[key_at..val_at].copy_from_slice(&key);
+                let mut val_buf = &mut map[val_at..];
+                let before = val_buf.len();
+                bincode::serialize_into(&mut val_buf, v).map_err(ConstDbError::Encode)?;
+                let val_len = before - val_buf.len();
+                LittleEndian::write_u32(&mut map[at + 12..at + 16], record_len(val_len as u64)?);
+
+                cursors[slot] = val_at + val_len;
+                Ok::<(), ConstDbError>(())
+            })?;
+
+            map.flush()?;
+            drop(map);
+            file.sync_all()?;
+            Ok(())
+        })
+    }
+}
+
+impl<K, V> ConstDb<K, V> {
+    /// Maps the database at `path` read-only.
+    ///
+    /// Only the header is checked here, records are bounds checked as they
+    /// are looked up. The file must not be modified while it is open.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ConstDbError> {
+        let file = File::open(path)?;
+        // SAFETY: the file is only ever read through this mapping, and the
+        // format is write-once; see the note on modifying the file above
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < HEADER_LEN {
+            return Err(if map.starts_with(&MAGIC[..map.len().min(4)]) {
+                ConstDbError::Truncated
+            } else {
+                ConstDbError::BadMagic
+            });
+        }
+        if map[0..4] != MAGIC {
+            return Err(ConstDbError::BadMagic);
+        }
+        let version = LittleEndian::read_u16(&map[4..6]);
+        if version != CONST_DB_VERSION {
+            return Err(ConstDbError::UnsupportedVersion(version));
+        }
+        if LittleEndian::read_u64(&map[28..36]) != header_checksum(&map[0..28])? {
+            return Err(ConstDbError::CorruptHeader);
+        }
+
+        let seed = LittleEndian::read_u32(&map[8..12]);
+        let slots = LittleEndian::read_u64(&map[12..20]);
+        let entries = LittleEndian::read_u64(&map[20..28]);
+        if slots == 0 {
+            return Err(ConstDbError::Corrupt("no slots".to_string()));
+        }
+        let dir_end = slots
+            .checked_mul(SLOT_LEN as u64)
+            .and_then(|len| len.checked_add(HEADER_LEN as u64));
+        if dir_end.is_none_or(|end| end > map.len() as u64) {
+            return Err(ConstDbError::Truncated);
+        }
+
+        Ok(ConstDb {
+            map,
+            seed,
+            slots,
+            entries,
+            _types: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    /// Seed the keys were placed with
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Finds the raw encoded value for an encoded key
+    fn find(&self, key: &[u8]) -> Result<Option<&[u8]>, ConstDbError> {
+        let hash = hash_mumur3(key, self.seed)?;
+        let slot = (hash % self.slots as u128) as usize;
+        let at = HEADER_LEN + slot * SLOT_LEN;
+        let mut offset =
+            usize::try_from(LittleEndian::read_u64(&self.map[at..at + 8])).unwrap_or(usize::MAX);
+        let count = LittleEndian::read_u32(&self.map[at + 8..at + 12]);
+
+        let out_of_bounds = || ConstDbError::Corrupt(format!("slot {slot} points past the end"));
+        for _ in 0..count {
+            // the offsets and lengths come from the file, so every sum is
+            // checked before it is used
+            let key_at = offset
+                .checked_add(RECORD_HEADER_LEN)
+                .ok_or_else(out_of_bounds)?;
+            let header = self.map.get(offset..key_at).ok_or_else(out_of_bounds)?;
+            let record_hash = LittleEndian::read_u64(&header[0..8]);
+            let key_len = LittleEndian::read_u32(&header[8..12]) as usize;
+            let val_len = LittleEndian::read_u32(&header[12..16]) as usize;
+            let val_at = key_at.checked_add(key_len).ok_or_else(out_of_bounds)?;
+            let end = val_at.checked_add(val_len).ok_or_else(out_of_bounds)?;
+            if end > self.map.len() {
+                return Err(out_of_bounds());
+            }
+
+            if record_hash == hash as u64 && &self.map[key_at..val_at] == key {
+                return Ok(Some(&self.map[val_at..end]));
+            }
+            offset = end;
+        }
+        Ok(None)
+    }
+
+    /// Borrows the encoded value straight out of the mapping, without
+    /// decoding it
+    pub fn get_bytes<Q>(&self, key: &Q) -> Result<Option<&[u8]>, ConstDbError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        let key = bincode::serialize(key).map_err(ConstDbError::Encode)?;
+        self.find(&key)
+    }
+
+    /// Looks a key up and decodes its value
+    pub fn get<Q>(&self, key: &Q) -> Result<Option<V>, ConstDbError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+        V: DeserializeOwned,
+    {
+        match self.get_bytes(key)? {
+            Some(bytes) => bincode::deserialize(bytes)
+                .map(Some)
+                .map_err(ConstDbError::Decode),
+            None => Ok(None),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> Result<bool, ConstDbError>
+    where
+        K: Borrow<Q>,
+        Q: Serialize + ?Sized,
+    {
+        Ok(self.get_bytes(key)?.is_some())
+    }
+}
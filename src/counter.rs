@@ -0,0 +1,328 @@
+// Frequency counting on top of HashDict
+//
+// A HashCounter<K> is a HashDict<K, usize> that only keeps positive counts:
+// a key counted down to zero is removed, and a key that was never counted
+// reads as 0. `most_common(n)` keeps the best n entries in a bounded min
+// heap, so it costs O(len * log n) instead of sorting every entry.
+
+use std::{
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fmt::{self, Debug},
+    iter::FromIterator,
+    ops::{Add, AddAssign, Sub, SubAssign},
+};
+
+use serde::Serialize;
+
+use crate::{Bucket, HashDict, RbTree};
+
+/// # Hash Counter
+///
+/// types: K and B, the kind of bucket as for `HashDict`.
+///
+/// public functions:
+/// - new
+/// - with_bucket_type
+/// - add
+/// - add_n
+/// - subtract
+/// - get
+/// - remove
+/// - most_common
+/// - total
+/// - len
+/// - iter
+/// - merge
+///
+/// `+`, `-`, `+=` and `-=` work on references to counters, subtraction
+/// drops the keys whose count would fall to zero or below.
+#[derive(Clone)]
+pub struct HashCounter<K, B = RbTree<K, usize>>
+where
+    K: PartialOrd + Serialize,
+{
+    dict: HashDict<K, usize, B>,
+    total: usize,
+}
+
+impl<K> HashCounter<K>
+where
+    K: PartialOrd + Serialize + Debug,
+{
+    /// Creates a counter of `len` `RbTree` buckets
+    pub fn new(len: usize, seed: u32) -> Self {
+        Self::with_bucket_type(len, seed)
+    }
+}
+
+impl<K, B> HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, usize>,
+{
+    pub fn with_bucket_type(len: usize, seed: u32) -> Self {
+        HashCounter {
+            dict: HashDict::with_bucket_type(len, seed),
+            total: 0,
+        }
+    }
+
+    /// Counts `key` once
+    pub fn add(&mut self, key: K) -> &mut Self {
+        self.add_n(key, 1)
+    }
+
+    /// Counts `key` `n` times
+    pub fn add_n(&mut self, key: K, n: usize) -> &mut Self {
+        if n == 0 {
+            return self;
+        }
+        match self.dict.get_mut(&key) {
+            Some(count) => *count += n,
+            None => {
+                self.dict.insert(key, n);
+            }
+        }
+        self.total += n;
+        self
+    }
+
+    /// Takes up to `n` off the count of `key`, removing it at zero.
+    /// Returns the count left.
+    pub fn subtract<Q>(&mut self, key: &Q, n: usize) -> usize
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let Some(count) = self.dict.get_mut(key) else {
+            return 0;
+        };
+        let taken = n.min(*count);
+        *count -= taken;
+        let left = *count;
+        if left == 0 {
+            self.dict.delete(key);
+        }
+        self.total -= taken;
+        left
+    }
+
+    /// Count of `key`, 0 if it was never counted
+    pub fn get<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.get_ref(key).copied().unwrap_or(0)
+    }
+
+    /// Forgets `key`, handing back its count
+    pub fn remove<Q>(&mut self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let count = self.dict.pop(key).unwrap_or(0);
+        self.total -= count;
+        count
+    }
+
+    /// The `n` highest counts, highest first. Equal counts come in key
+    /// order.
+    pub fn most_common(&self, n: usize) -> Vec<(&K, usize)> {
+        if n == 0 {
+            return Vec::new();
+        }
+        // min heap of the best n so far, its top is the one to drop next
+        let mut heap: BinaryHeap<Reverse<Ranked<'_, K>>> = BinaryHeap::with_capacity(n + 1);
+        for (key, &count) in self.dict.entries() {
+            heap.push(Reverse(Ranked { count, key }));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(r)| (r.key, r.count))
+            .collect()
+    }
+
+    /// Sum of all counts
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Number of distinct keys
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    /// Adds every count of `other` to this counter
+    pub fn merge(&mut self, other: &Self)
+    where
+        K: Clone,
+    {
+        for (key, &count) in other.dict.entries() {
+            self.add_n(key.clone(), count);
+        }
+    }
+
+    /// Borrows every key with its count, in bucket order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, usize)> {
+        self.dict.entries().map(|(k, &count)| (k, count))
+    }
+
+    // same bucket count and seed, no entries
+    fn empty_like(&self) -> Self {
+        HashCounter::with_bucket_type(self.dict.bucket_count(), self.dict.seed())
+    }
+}
+
+// orders by count, then by key with the smaller key ranking higher
+struct Ranked<'a, K> {
+    count: usize,
+    key: &'a K,
+}
+
+impl<K: PartialOrd> Ord for Ranked<'_, K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.count
+            .cmp(&other.count)
+            .then_with(|| other.key.partial_cmp(self.key).unwrap_or(Ordering::Equal))
+    }
+}
+
+impl<K: PartialOrd> PartialOrd for Ranked<'_, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: PartialOrd> PartialEq for Ranked<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: PartialOrd> Eq for Ranked<'_, K> {}
+
+// counter arithmetic
+impl<K, B> AddAssign<&HashCounter<K, B>> for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    B: Bucket<K, usize>,
+{
+    fn add_assign(&mut self, other: &HashCounter<K, B>) {
+        self.merge(other);
+    }
+}
+
+impl<K, B> SubAssign<&HashCounter<K, B>> for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, usize>,
+{
+    fn sub_assign(&mut self, other: &HashCounter<K, B>) {
+        for (key, &count) in other.dict.entries() {
+            self.subtract(key, count);
+        }
+    }
+}
+
+impl<K, B> Add for &HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    B: Bucket<K, usize>,
+{
+    type Output = HashCounter<K, B>;
+
+    fn add(self, other: &HashCounter<K, B>) -> HashCounter<K, B> {
+        let mut sum = self.empty_like();
+        sum.merge(self);
+        sum.merge(other);
+        sum
+    }
+}
+
+impl<K, B> Sub for &HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    B: Bucket<K, usize>,
+{
+    type Output = HashCounter<K, B>;
+
+    fn sub(self, other: &HashCounter<K, B>) -> HashCounter<K, B> {
+        let mut diff = self.empty_like();
+        for (key, count) in self.iter() {
+            let left = count.saturating_sub(other.get(key));
+            diff.add_n(key.clone(), left);
+        }
+        diff
+    }
+}
+
+// std collection traits
+impl<K, B> Default for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, usize>,
+{
+    fn default() -> Self {
+        HashCounter {
+            dict: HashDict::default(),
+            total: 0,
+        }
+    }
+}
+
+impl<K, B> FromIterator<K> for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, usize>,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut counter = HashCounter::default();
+        counter.extend(iter);
+        counter
+    }
+}
+
+impl<K, B> Extend<K> for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, usize>,
+{
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.add(key);
+        }
+    }
+}
+
+/// Two counters are equal when they hold the same counts, regardless of
+/// their bucket count or seed
+impl<K, B> PartialEq for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, usize>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.dict == other.dict
+    }
+}
+
+impl<K, B> Debug for HashCounter<K, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    B: Bucket<K, usize>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.dict, f)
+    }
+}
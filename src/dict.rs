@@ -0,0 +1,166 @@
+// Common interface of the murmur3 hash tables
+//
+// HashDict (chained buckets) and RobinHoodDict (open addressing) take the
+// same calls, so code written against `Dict` can pick its backend per table:
+//
+//   fn count_words<D: Dict<String, u32>>(words: &[&str]) -> D { ... }
+//   let chained: HashDict<String, u32> = count_words(&words);
+//   let open: RobinHoodDict<String, u32> = count_words(&words);
+
+use std::{borrow::Borrow, fmt::Debug};
+
+use serde::Serialize;
+
+use crate::{Bucket, HashDict};
+
+// size and seed of a table made by `Default`, and therefore by `collect()`
+pub(crate) const DEFAULT_LEN: usize = 1024;
+pub(crate) const DEFAULT_SEED: u32 = 42;
+
+pub trait Dict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    /// Creates a table hashing with `seed`. What `len` sizes depends on
+    /// the backend: a `HashDict` gets `len` buckets and never adds more,
+    /// so it slows down once it holds many more entries than that. A
+    /// `RobinHoodDict` gets room for `len` entries and doubles when it
+    /// runs out.
+    fn new(len: usize, seed: u32) -> Self
+    where
+        Self: Sized;
+
+    /// Inserts a key-value pair, replacing the value if the key is
+    /// already in the table
+    fn insert(&mut self, key: K, value: V) -> &mut Self;
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized;
+
+    fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized;
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized;
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized;
+
+    fn len(&self) -> usize;
+
+    fn seed(&self) -> u32;
+
+    fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        V: Clone,
+    {
+        self.get_ref(key).cloned()
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.get_ref(key).is_some()
+    }
+
+    /// Runs `f` on the value stored under `key`, if there is one
+    fn update<Q, F>(&mut self, key: &Q, mut f: F)
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        F: FnMut(&mut V),
+    {
+        if let Some(v) = self.get_mut(key) {
+            f(v);
+        }
+    }
+
+    fn delete<Q>(&mut self, key: &Q) -> &mut Self
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.remove_entry(key);
+        self
+    }
+
+    fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V, B> Dict<K, V> for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    /// `len` is the bucket count, the table never grows
+    fn new(len: usize, seed: u32) -> Self {
+        HashDict::with_bucket_type(len, seed)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> &mut Self {
+        HashDict::insert(self, key, value)
+    }
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        HashDict::get_key_value(self, key)
+    }
+
+    fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        HashDict::get_ref(self, key)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        HashDict::get_mut(self, key)
+    }
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        HashDict::remove_entry(self, key)
+    }
+
+    fn len(&self) -> usize {
+        HashDict::len(self)
+    }
+
+    fn seed(&self) -> u32 {
+        HashDict::seed(self)
+    }
+}
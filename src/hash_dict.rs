@@ -1,120 +1,432 @@
-use std::fmt::Debug;
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    iter::FromIterator,
+    marker::PhantomData,
+    ops::Index,
+};
 
 use serde::Serialize;
 
-use crate::HashDictIter;
+use crate::{
+    dict::{DEFAULT_LEN, DEFAULT_SEED},
+    sparse::SparseBuckets,
+    Bucket, HashDictIter,
+};
 
-use super::{hash_anything, HashDict, RbTree};
+use super::{hash_anything, HashDict};
 
 impl<K, V> HashDict<K, V>
 where
-    K: PartialOrd + Serialize + Debug + Clone,
-    V: PartialOrd + Clone + Debug,
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
 {
+    /// Creates a table of `len` `RbTree` buckets, use `with_bucket_type`
+    /// for the other kinds of bucket
     pub fn new(len: usize, seed: u32) -> Self {
-        let mut new_table: Vec<RbTree<K, V>> = Vec::new();
-        for _ in 0..len {
-            new_table.push(RbTree::new());
-        }
+        Self::with_bucket_type(len, seed)
+    }
+}
+
+impl<K, V, B> HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    /// Creates a table of `len` buckets of type `B`, e.g.
+    /// `HashDict::<String, u32, SmallBucket<_, _>>::with_bucket_type(64, 42)`
+    pub fn with_bucket_type(len: usize, seed: u32) -> Self {
         HashDict {
             arr_length: len,
             seed,
             tab_length: 0,
-            table: new_table,
+            table: SparseBuckets::new(len),
+            _entries: PhantomData,
         }
     }
 
+    /// Inserts a key-value pair, replacing the value if the key is
+    /// already in the table
     pub fn insert(&mut self, key: K, value: V) -> &mut Self {
-        let seed = self.seed;
-        if let Ok(index) = hash_anything(&key, seed) {
-            let resize_index: usize = (index % (self.arr_length as u128)) as usize;
-
-            self.table[resize_index].insert(key, value);
-            self.tab_length += 1;
+        if let Some(index) = self.bucket_index(&key) {
+            if self.table.get_or_insert(index).insert(key, value).is_none() {
+                self.tab_length += 1;
+            }
         }
 
-        return self;
+        self
     }
 
-    pub fn get(&self, key: &K) -> Option<V> {
-        if let Ok(index) = hash_anything(key, self.seed) {
-            let resize_index: usize = (index % (self.arr_length as u128)) as usize;
+    /// Looks up a value by any borrowed form of the key, so a
+    /// `HashDict<String, _>` can be queried with a `&str` and a
+    /// `HashDict<Vec<u8>, _>` with a `&[u8]`
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        V: Clone,
+    {
+        self.get_ref(key).cloned()
+    }
 
-            let res = self.table[resize_index].get(&key);
-            return res;
-        }
-        None
+    /// Borrows the value instead of cloning it
+    pub fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    /// Mutably borrows the value stored under `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let index = self.bucket_index(key)?;
+        self.table.get_mut(index)?.get_mut(key)
     }
 
-    pub fn delete(&mut self, key: &K) -> &mut Self {
-        if let Ok(hash) = hash_anything(&key, self.seed) {
-            let index: usize = (hash % (self.arr_length as u128)) as usize;
+    /// Borrows the stored key along with its value
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let index = self.bucket_index(key)?;
+        self.table.get(index)?.get_key_value(key)
+    }
 
-            if self.table[index].key_exist(key.clone()) {
-                self.table[index].delete(key.clone());
-                self.tab_length -= 1;
-            }
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.get_ref(key).is_some()
+    }
+
+    /// Runs `f` on the value stored under `key`, if there is one
+    pub fn update<Q, F>(&mut self, key: &Q, mut f: F)
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        F: FnMut(&mut V),
+    {
+        if let Some(v) = self.get_mut(key) {
+            f(v);
         }
-        return self;
     }
 
-    pub fn pop(&mut self, key: &K) -> Option<V> {
-        if let Some(val) = self.get(key) {
-            self.delete(key);
-            return Some(val);
+    pub fn delete<Q>(&mut self, key: &Q) -> &mut Self
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.remove_entry(key);
+        self
+    }
+
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Removes a key from the table, handing back the stored key and value
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let index = self.bucket_index(key)?;
+        let bucket = self.table.get_mut(index)?;
+        let entry = bucket.remove_entry(key)?;
+        if bucket.is_empty() {
+            self.table.remove(index);
         }
-        None
+        self.tab_length -= 1;
+        Some(entry)
     }
 
     pub fn len(&self) -> usize {
         self.tab_length
     }
 
-    pub fn get_mut<F>(&mut self, key: &K, f: F)
+    pub fn is_empty(&self) -> bool {
+        self.tab_length == 0
+    }
+
+    /// Number of buckets in the table
+    pub fn bucket_count(&self) -> usize {
+        self.arr_length
+    }
+
+    /// Removes every entry, keeping the bucket count and seed
+    pub fn clear(&mut self) -> &mut Self {
+        self.table.clear();
+        self.tab_length = 0;
+        self
+    }
+
+    /// Keeps only the entries `f` returns true for
+    pub fn retain<F>(&mut self, mut f: F) -> &mut Self
     where
-        F: FnMut(&mut V),
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.extract_if(|k, v| !f(k, v));
+        self
+    }
+
+    /// Takes out every entry `f` returns true for, bucket by bucket. Each
+    /// bucket is walked once and, if it lost entries, rebuilt once.
+    pub fn extract_if<F>(&mut self, mut f: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
     {
-        if let Ok(hash) = hash_anything(key, self.seed) {
-            let index = (hash % (self.arr_length as u128)) as usize;
-            self.table[index].get_mut(key, f);
+        let mut extracted = Vec::new();
+        for bucket in self.table.iter_mut() {
+            extracted.extend(bucket.extract_if(&mut f));
         }
+        self.table.retain(|bucket| !bucket.is_empty());
+        self.tab_length -= extracted.len();
+        extracted
+    }
+
+    /// Removes every key in `keys` and hands back the entries that were
+    /// there. The keys are grouped by bucket first, so every bucket is
+    /// rebuilt at most once however many of its keys go.
+    pub fn remove_many<'q, Q, I>(&mut self, keys: I) -> Vec<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized + 'q,
+        I: IntoIterator<Item = &'q Q>,
+    {
+        let mut by_bucket: Vec<(usize, &Q)> = keys
+            .into_iter()
+            .filter_map(|key| Some((self.bucket_index(key)?, key)))
+            .collect();
+        by_bucket.sort_by_key(|&(index, _)| index);
+
+        let mut removed = Vec::new();
+        for group in by_bucket.chunk_by(|a, b| a.0 == b.0) {
+            let index = group[0].0;
+            let Some(bucket) = self.table.get_mut(index) else {
+                continue;
+            };
+            removed
+                .extend(bucket.extract_if(|k, _| group.iter().any(|&(_, key)| k.borrow() == key)));
+            if bucket.is_empty() {
+                self.table.remove(index);
+            }
+        }
+        self.tab_length -= removed.len();
+        removed
+    }
+
+    /// Releases the memory the buckets kept around for deleted entries
+    pub fn shrink_to_fit(&mut self) {
+        self.table.shrink_to_fit(|bucket| bucket.shrink_to_fit());
+    }
+
+    /// Seed the keys are hashed with
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Lends every entry to `f` bucket by bucket, stopping at the first error
+    pub(crate) fn try_for_each<E, F>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&K, &V) -> Result<(), E>,
+    {
+        for bucket in self.table.iter() {
+            for (k, v) in bucket.iter() {
+                f(k, v)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Borrows every entry, bucket by bucket
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.table.iter().flat_map(|bucket| bucket.iter())
+    }
+
+    /// Picks the bucket for a key.
+    ///
+    /// Keys are hashed through their serde encoding, and serde encodes the
+    /// owned and borrowed forms of a type the same way (`String`/`str`,
+    /// `Vec<T>`/`[T]`, `Box<T>`/`T`, ...), so a borrowed key lands in the
+    /// same bucket as the owned key it was derived from. A `Borrow` impl
+    /// whose two sides serialize differently (e.g. `[u8; N]` -> `[u8]`)
+    /// will not find its entries.
+    fn bucket_index<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Serialize + ?Sized,
+    {
+        let hash = hash_anything(key, self.seed).ok()?;
+        Some((hash % (self.arr_length as u128)) as usize)
     }
 }
 
 // developing iters
-impl<K, V> HashDict<K, V>
+impl<K, V, B> HashDict<K, V, B>
 where
     K: PartialOrd + PartialEq + Debug + Clone + Serialize,
-    V: PartialEq + PartialOrd + Debug + Clone,
+    V: Debug + Clone,
+    B: Bucket<K, V>,
 {
     fn stack_items(&self) -> Vec<(K, V)> {
-        let mut stack: Vec<(K, V)> = Vec::new();
-        for tree in self.table.iter() {
-            if tree.len() > 0 {
-                stack.extend(tree.iter());
-            }
+        let mut stack: Vec<(K, V)> = Vec::with_capacity(self.tab_length);
+        for bucket in self.table.iter() {
+            stack.extend(bucket.iter().map(|(k, v)| (k.clone(), v.clone())));
         }
-        return stack;
+        stack
     }
 
     pub fn iter(&self) -> HashDictIter<K, V> {
         let mut stack = self.stack_items();
         stack.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
-        return HashDictIter {
+        HashDictIter {
             iter: stack.into_iter(),
-        };
+        }
+    }
+}
+
+impl<K, V> Iterator for HashDictIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
     }
 }
 
-impl<K, V> Iterator for HashDictIter<K, V>
+// std collection traits
+impl<K, V, B> Default for HashDict<K, V, B>
 where
-    K: Debug + Clone,
-    V: Debug + Clone,
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    fn default() -> Self {
+        HashDict::with_bucket_type(DEFAULT_LEN, DEFAULT_SEED)
+    }
+}
+
+impl<K, V, B> FromIterator<(K, V)> for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut dict = HashDict::default();
+        dict.extend(iter);
+        dict
+    }
+}
+
+impl<K, V, B> Extend<(K, V)> for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+/// Moves the entries out in key order, without cloning them
+impl<K, V, B> IntoIterator for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
 {
     type Item = (K, V);
+    type IntoIter = HashDictIter<K, V>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        return self.iter.next();
+    fn into_iter(self) -> Self::IntoIter {
+        let mut entries = Vec::with_capacity(self.tab_length);
+        for mut bucket in self.table {
+            entries.extend(bucket.extract_if(|_, _| true));
+        }
+        entries.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        HashDictIter {
+            iter: entries.into_iter(),
+        }
+    }
+}
+
+impl<K, V, B> IntoIterator for &HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Clone + Debug,
+    B: Bucket<K, V>,
+{
+    type Item = (K, V);
+    type IntoIter = HashDictIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, Q, V, B> Index<&Q> for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug + Borrow<Q>,
+    Q: PartialOrd + Serialize + ?Sized,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    type Output = V;
+
+    /// Panics if the key is not in the table
+    fn index(&self, key: &Q) -> &V {
+        self.get_ref(key).expect("key not found in HashDict")
+    }
+}
+
+/// Two tables are equal when they hold the same entries, regardless of
+/// their bucket count or seed
+impl<K, V, B> PartialEq for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: PartialEq + Debug,
+    B: Bucket<K, V>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .table
+                .iter()
+                .flat_map(|bucket| bucket.iter())
+                .all(|(k, v)| other.get_ref(k) == Some(v))
+    }
+}
+
+impl<K, V, B> Eq for HashDict<K, V, B>
+where
+    K: Eq + PartialOrd + Serialize + Debug,
+    V: Eq + Debug,
+    B: Bucket<K, V>,
+{
+}
+
+impl<K, V, B> Debug for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Clone + Debug,
+    B: Bucket<K, V>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
     }
 }
 
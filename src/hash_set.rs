@@ -0,0 +1,268 @@
+// Set of keys on top of HashDict
+//
+// A HashSetDict<K> is a HashDict<K, ()> with a set-shaped API, so it hashes
+// with murmur3 and can use any of the bucket kinds. Nothing is asked of the
+// (absent) values, and iteration borrows the keys instead of cloning and
+// sorting them the way HashDict::iter does.
+
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    iter::FromIterator,
+};
+
+use serde::Serialize;
+
+use crate::{sparse::SparseIter, Bucket, HashDict, RbTree};
+
+/// # Hash Set Dictionary
+///
+/// types: K and B, the kind of bucket as for `HashDict`.
+///
+/// Iteration follows the bucket order, not the key order.
+///
+/// public functions:
+/// - new
+/// - with_bucket_type
+/// - insert
+/// - contains
+/// - remove
+/// - len
+/// - iter
+/// - union
+/// - intersection
+/// - difference
+/// - symmetric_difference
+/// - is_subset
+/// - is_superset
+/// - is_disjoint
+#[derive(Clone)]
+pub struct HashSetDict<K, B = RbTree<K, ()>>
+where
+    K: PartialOrd + Serialize,
+{
+    dict: HashDict<K, (), B>,
+}
+
+impl<K> HashSetDict<K>
+where
+    K: PartialOrd + Serialize + Debug,
+{
+    /// Creates a set of `len` `RbTree` buckets
+    pub fn new(len: usize, seed: u32) -> Self {
+        Self::with_bucket_type(len, seed)
+    }
+}
+
+impl<K, B> HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    pub fn with_bucket_type(len: usize, seed: u32) -> Self {
+        HashSetDict {
+            dict: HashDict::with_bucket_type(len, seed),
+        }
+    }
+
+    /// Adds a key, returns false if it was already in the set
+    pub fn insert(&mut self, key: K) -> bool {
+        let before = self.dict.len();
+        self.dict.insert(key, ());
+        self.dict.len() > before
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.contains_key(key)
+    }
+
+    /// Removes a key, returns false if it wasn't in the set
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.remove_entry(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.dict.seed()
+    }
+
+    pub fn iter(&self) -> HashSetIter<'_, K, B> {
+        HashSetIter {
+            buckets: self.dict.table.iter(),
+            bucket: None,
+            remaining: self.len(),
+        }
+    }
+
+    /// Keys in either set, each once
+    pub fn union<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        self.iter().chain(other.difference(self))
+    }
+
+    /// Keys in both sets
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        // walk the smaller set, probe the larger one
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small.iter().filter(move |k| large.contains(*k))
+    }
+
+    /// Keys in `self` but not in `other`
+    pub fn difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        self.iter().filter(move |k| !other.contains(*k))
+    }
+
+    /// Keys in exactly one of the two sets
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        self.difference(other).chain(other.difference(self))
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|k| other.contains(k))
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).next().is_none()
+    }
+}
+
+/// Borrowing iterator over the keys of a `HashSetDict`
+pub struct HashSetIter<'a, K, B>
+where
+    K: PartialOrd + 'a,
+    B: Bucket<K, ()> + 'a,
+{
+    buckets: SparseIter<'a, B>,
+    bucket: Option<B::Iter<'a>>,
+    remaining: usize,
+}
+
+impl<'a, K, B> Iterator for HashSetIter<'a, K, B>
+where
+    K: PartialOrd + 'a,
+    B: Bucket<K, ()> + 'a,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        loop {
+            if let Some((key, _)) = self.bucket.as_mut().and_then(|bucket| bucket.next()) {
+                self.remaining -= 1;
+                return Some(key);
+            }
+            self.bucket = Some(self.buckets.next()?.iter());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, B> ExactSizeIterator for HashSetIter<'a, K, B>
+where
+    K: PartialOrd + 'a,
+    B: Bucket<K, ()> + 'a,
+{
+}
+
+impl<'a, K, B> IntoIterator for &'a HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    type Item = &'a K;
+    type IntoIter = HashSetIter<'a, K, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// std collection traits
+impl<K, B> Default for HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    fn default() -> Self {
+        HashSetDict {
+            dict: HashDict::default(),
+        }
+    }
+}
+
+impl<K, B> FromIterator<K> for HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = HashSetDict::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K, B> Extend<K> for HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+/// Two sets are equal when they hold the same keys, regardless of their
+/// bucket count or seed
+impl<K, B> PartialEq for HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<K, B> Eq for HashSetDict<K, B>
+where
+    K: Eq + PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+}
+
+impl<K, B> Debug for HashSetDict<K, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    B: Bucket<K, ()>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,34 @@
+// Jump consistent hash (Lamping & Veach, 2014)
+//
+// Maps a key to one of `shards` numbered buckets without storing anything.
+// Going from n to n + 1 shards moves about 1/(n + 1) of the keys, all of
+// them to the new shard. Shards can only be added or removed at the end,
+// so it suits numbered partitions rather than named members that come and
+// go, use a `HashRing` or `RendezvousHash` for those.
+
+use serde::Serialize;
+
+use crate::hash_anything;
+
+/// The shard in `0..shards` that `key` belongs to, `None` when there are
+/// no shards
+pub fn jump_hash<Q: Serialize + ?Sized>(key: &Q, shards: u32, seed: u32) -> Option<u32> {
+    if shards == 0 {
+        return None;
+    }
+    let hash = hash_anything(key, seed).ok()? as u64;
+    Some(jump(hash, shards))
+}
+
+// the paper's loop, jumping forward through the shards the key would
+// move to as the shard count grows
+fn jump(mut key: u64, shards: u32) -> u32 {
+    let mut b: i64 = -1;
+    let mut j: i64 = 0;
+    while j < shards as i64 {
+        b = j;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as u32
+}
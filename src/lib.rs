@@ -1,7 +1,6 @@
 use std::{
-    cell::RefCell,
     fmt::Debug,
-    rc::Rc,
+    marker::PhantomData,
     vec::{self},
 };
 
@@ -28,16 +27,25 @@ enum Color {
     Black,
 }
 
-type Ptr<K, V> = Rc<RefCell<RbNode<K, V>>>;
+// index of a node in its tree's arena, u32 keeps the nodes small and a
+// tree can't hold more than `i32::MAX` entries anyway
+type NodeId = u32;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Debug)]
 struct RbNode<K: PartialOrd, V: Debug> {
     val: V,
     key: K,
     color: Color,
-    parent: Option<Ptr<K, V>>,
-    left_child: Option<Ptr<K, V>>,
-    right_child: Option<Ptr<K, V>>,
+    parent: Option<NodeId>,
+    left_child: Option<NodeId>,
+    right_child: Option<NodeId>,
+}
+
+// A vacant slot links to the next free one, so freed slots get reused
+#[derive(Clone, Debug)]
+enum Slot<K: PartialOrd, V: Debug> {
+    Occupied(RbNode<K, V>),
+    Vacant(Option<NodeId>),
 }
 
 pub struct RbIter<K, V>
@@ -48,6 +56,14 @@ where
     iter: vec::IntoIter<(K, V)>,
 }
 
+/// Borrowing in-order iterator over an `RbTree`
+pub struct RbRefIter<'a, K: PartialOrd, V: Debug> {
+    tree: &'a RbTree<K, V>,
+    // the nodes whose left subtree is done but which aren't yielded yet
+    stack: Vec<NodeId>,
+    remaining: usize,
+}
+
 /// # Red Black Tree
 ///
 /// types: K, V
@@ -59,30 +75,60 @@ where
 /// - new
 /// - key_exists
 /// - get
+/// - get_ref
+/// - get_mut
+/// - get_key_value
+/// - update
+/// - contains_key
+/// - remove_entry
+/// - first
+/// - pop_first
+/// - ceiling
+/// - extract_if
+/// - retain
+/// - from_sorted_vec
 /// - len
-#[derive(Debug, Clone)]
+/// - is_empty
+/// - iter
+/// - iter_ref
+/// - into_sorted_vec
+/// - height
+/// - capacity
+/// - shrink_to_fit
+#[derive(Clone, Debug)]
 pub struct RbTree<K: PartialOrd, V: Debug> {
-    root: Option<Ptr<K, V>>,
+    // every node lives in here and links to the others by index
+    nodes: Vec<Slot<K, V>>,
+    free: Option<NodeId>,
+    root: Option<NodeId>,
     length: i32,
 }
 
 // -------------------- HASH TABLE --------------------
 mod murmur;
-use murmur::hash_anything;
+pub use murmur::{hash_anything, hash_mumur3};
 use serde::Serialize;
 
 mod hash_dict;
+mod sparse;
+use sparse::SparseBuckets;
 
-pub struct HashDictIter<K, V>
-where
-    K: Debug + Clone,
-    V: Debug + Clone,
-{
+mod bucket;
+pub use bucket::{
+    Bucket, HybridBucket, HybridBucketIter, SmallBucket, SmallBucketIter, TREEIFY_THRESHOLD,
+    UNTREEIFY_THRESHOLD,
+};
+
+pub struct HashDictIter<K, V> {
     iter: vec::IntoIter<(K, V)>,
 }
 
-#[derive(Debug, Clone)]
-pub struct HashDict<K, V>
+/// # Hash Dictionary
+///
+/// types: K, V and B, the kind of bucket colliding keys are kept in. See
+/// `Bucket` for the choices, buckets are `RbTree`s unless told otherwise.
+#[derive(Clone)]
+pub struct HashDict<K, V, B = RbTree<K, V>>
 where
     K: PartialOrd + Serialize,
     V: Debug,
@@ -90,5 +136,79 @@ where
     arr_length: usize,
     seed: u32,
     tab_length: usize,
-    table: Vec<RbTree<K, V>>,
+    table: SparseBuckets<B>,
+    _entries: PhantomData<fn() -> (K, V)>,
 }
+
+mod stats;
+pub use stats::HashDictStats;
+
+mod dict;
+pub use dict::Dict;
+
+mod merge;
+pub use merge::HashDictDiff;
+
+// -------------------- HASH SET --------------------
+mod hash_set;
+pub use hash_set::{HashSetDict, HashSetIter};
+
+// -------------------- MULTI-MAP --------------------
+mod multi_dict;
+pub use multi_dict::HashMultiDict;
+
+// -------------------- COUNTER --------------------
+mod counter;
+pub use counter::HashCounter;
+
+// -------------------- OPEN ADDRESSING --------------------
+mod robin_hood;
+pub use robin_hood::RobinHoodDict;
+
+// -------------------- LRU CACHE --------------------
+mod lru;
+pub use lru::{EvictionCallback, LruDict, LruIter, Weigher};
+
+// -------------------- EXPIRY --------------------
+mod ttl;
+pub use ttl::{Clock, ManualClock, SystemClock, TtlDict};
+
+// -------------------- CONSISTENT HASHING --------------------
+mod ring;
+pub use ring::HashRing;
+
+mod jump;
+pub use jump::jump_hash;
+
+mod rendezvous;
+pub use rendezvous::RendezvousHash;
+
+// -------------------- PROBABILISTIC --------------------
+mod bloom;
+pub use bloom::{BloomError, BloomFilter, BLOOM_VERSION};
+
+mod sketch;
+pub use sketch::{CountMinSketch, SketchError};
+
+mod top_k;
+pub use top_k::TopK;
+
+// -------------------- CONCURRENT HASH TABLE --------------------
+mod concurrent;
+pub use concurrent::{ConcurrentHashDict, ShardEntry, ShardGuard};
+
+// -------------------- SNAPSHOTS --------------------
+mod snapshot;
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
+
+// -------------------- CONSTANT DB --------------------
+mod const_db;
+pub use const_db::{ConstDb, ConstDbError, CONST_DB_VERSION};
+
+// -------------------- WRITE-AHEAD LOG --------------------
+mod wal;
+pub use wal::{DurableHashDict, FsyncPolicy, WalError, WAL_VERSION};
+
+// -------------------- SERDE --------------------
+#[cfg(feature = "serde-impls")]
+mod serde_impl;
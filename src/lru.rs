@@ -0,0 +1,433 @@
+// Least-recently-used cache on the murmur3 tables
+//
+// Entries live in an arena of nodes linked into a recency list, most
+// recently used at the head. The key is stored in its node and nowhere
+// else: each node also keeps the key's murmur3 hash, and the index is an
+// array of buckets indexed by that hash, each holding the head of a chain
+// of nodes linked through the nodes themselves. The array doubles once
+// there are more entries than buckets, relinking the nodes by their stored
+// hash, so chains stay short and `get`, `put` and eviction are all O(1):
+// find the node, unlink it, push it back at the head, or drop the tail
+// until the cache fits again.
+//
+// The capacity is a total weight, every entry weighs 1 unless a weigher is
+// given, e.g. `|_, v: &Vec<u8>| v.len()` to bound the cache in bytes.
+
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    mem,
+};
+
+use serde::Serialize;
+
+use crate::hash_anything;
+
+// u32 like RbTree's arena, a cache never holds 4 billion entries
+type NodeId = u32;
+
+// buckets of the index once the first entry goes in
+const MIN_BUCKETS: usize = 16;
+
+struct LruNode<K, V> {
+    key: K,
+    val: V,
+    weight: usize,
+    hash: u128,
+    prev: Option<NodeId>,
+    next: Option<NodeId>,
+    // next node in the same index bucket
+    bucket_next: Option<NodeId>,
+}
+
+pub type Weigher<K, V> = Box<dyn Fn(&K, &V) -> usize>;
+pub type EvictionCallback<K, V> = Box<dyn FnMut(K, V)>;
+
+/// # LRU Dictionary
+///
+/// A cache of fixed total weight that drops the least recently used entries
+/// to make room.
+///
+/// public functions:
+/// - new
+/// - with_weigher
+/// - set_on_evict
+/// - put
+/// - get
+/// - get_mut
+/// - peek
+/// - pop
+/// - contains_key
+/// - len
+/// - weight
+/// - capacity
+/// - iter
+pub struct LruDict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    // first node of each bucket's chain
+    buckets: Vec<Option<NodeId>>,
+    seed: u32,
+    nodes: Vec<Option<LruNode<K, V>>>,
+    free: Vec<NodeId>,
+    head: Option<NodeId>,
+    tail: Option<NodeId>,
+    weight: usize,
+    capacity: usize,
+    weigher: Weigher<K, V>,
+    on_evict: Option<EvictionCallback<K, V>>,
+}
+
+impl<K, V> LruDict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    /// Creates a cache holding at most `capacity` entries
+    pub fn new(capacity: usize, seed: u32) -> Self {
+        Self::with_weigher(capacity, seed, |_, _| 1)
+    }
+
+    /// Creates a cache whose entries weigh `weigher(key, value)`, and whose
+    /// entries together weigh at most `capacity`
+    pub fn with_weigher<F>(capacity: usize, seed: u32, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> usize + 'static,
+    {
+        LruDict {
+            buckets: Vec::new(),
+            seed,
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: None,
+            tail: None,
+            weight: 0,
+            capacity,
+            weigher: Box::new(weigher),
+            on_evict: None,
+        }
+    }
+
+    /// Calls `f` with every entry evicted to make room. Entries replaced
+    /// by `put` or taken out by `pop` don't count as evicted.
+    pub fn set_on_evict<F>(&mut self, f: F)
+    where
+        F: FnMut(K, V) + 'static,
+    {
+        self.on_evict = Some(Box::new(f));
+    }
+
+    /// Inserts a key-value pair as the most recently used entry, handing
+    /// back the value it replaced. Evicts from the least recently used end
+    /// until the cache fits, which takes the new entry itself if it alone
+    /// weighs more than the capacity.
+    pub fn put(&mut self, key: K, value: V) -> Option<V> {
+        let weight = (self.weigher)(&key, &value);
+        let old = match self.find(&key) {
+            Some(id) => {
+                let node = self.node_mut(id);
+                let old = mem::replace(&mut node.val, value);
+                let old_weight = mem::replace(&mut node.weight, weight);
+                self.weight = self.weight - old_weight + weight;
+                self.unlink(id);
+                self.push_front(id);
+                Some(old)
+            }
+            None => {
+                // same as HashDict::insert, a key that can't be hashed
+                // isn't stored
+                let hash = hash_anything(&key, self.seed).ok()?;
+                if self.len() >= self.buckets.len() {
+                    self.grow_index();
+                }
+                let id = self.alloc(LruNode {
+                    key,
+                    val: value,
+                    weight,
+                    hash,
+                    prev: None,
+                    next: None,
+                    bucket_next: None,
+                });
+                self.link_bucket(id);
+                self.weight += weight;
+                self.push_front(id);
+                None
+            }
+        };
+        self.evict();
+        old
+    }
+
+    /// Looks up `key` and marks it as the most recently used entry
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let id = self.touch(key)?;
+        Some(&self.node(id).val)
+    }
+
+    /// Like `get`, but lends the value mutably. Its weight is not looked
+    /// at again, `put` the new value if that changes it.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let id = self.touch(key)?;
+        Some(&mut self.node_mut(id).val)
+    }
+
+    /// Looks up `key` without touching its recency
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let id = self.find(key)?;
+        Some(&self.node(id).val)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// Takes an entry out of the cache
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let id = self.find(key)?;
+        Some(self.remove_node(id).val)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total weight of the entries
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Borrows the entries from most to least recently used
+    pub fn iter(&self) -> LruIter<'_, K, V> {
+        LruIter {
+            dict: self,
+            next: self.head,
+            remaining: self.len(),
+        }
+    }
+
+    fn touch<Q>(&mut self, key: &Q) -> Option<NodeId>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let id = self.find(key)?;
+        if self.head != Some(id) {
+            self.unlink(id);
+            self.push_front(id);
+        }
+        Some(id)
+    }
+
+    fn evict(&mut self) {
+        while self.weight > self.capacity {
+            let Some(id) = self.tail else {
+                break;
+            };
+            let node = self.remove_node(id);
+            if let Some(on_evict) = &mut self.on_evict {
+                on_evict(node.key, node.val);
+            }
+        }
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<NodeId>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let hash = hash_anything(key, self.seed).ok()?;
+        let mut id = self.buckets[self.bucket_of(hash)]?;
+        loop {
+            let node = self.node(id);
+            if node.hash == hash && node.key.borrow() == key {
+                return Some(id);
+            }
+            id = node.bucket_next?;
+        }
+    }
+
+    fn remove_node(&mut self, id: NodeId) -> LruNode<K, V> {
+        self.unlink(id);
+        let node = self.release(id);
+        // take the node out of its bucket's chain
+        let bucket = self.bucket_of(node.hash);
+        let mut prev = self.buckets[bucket].expect("node is indexed");
+        if prev == id {
+            self.buckets[bucket] = node.bucket_next;
+        } else {
+            while self.node(prev).bucket_next != Some(id) {
+                prev = self.node(prev).bucket_next.expect("node is chained");
+            }
+            self.node_mut(prev).bucket_next = node.bucket_next;
+        }
+        self.weight -= node.weight;
+        node
+    }
+}
+
+/// RECENCY LIST
+impl<K, V> LruDict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    fn node(&self, id: NodeId) -> &LruNode<K, V> {
+        match &self.nodes[id as usize] {
+            Some(node) => node,
+            None => unreachable!("dangling LruDict node {id}"),
+        }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut LruNode<K, V> {
+        match &mut self.nodes[id as usize] {
+            Some(node) => node,
+            None => unreachable!("dangling LruDict node {id}"),
+        }
+    }
+
+    fn alloc(&mut self, node: LruNode<K, V>) -> NodeId {
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id as usize] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as NodeId
+            }
+        }
+    }
+
+    fn release(&mut self, id: NodeId) -> LruNode<K, V> {
+        let node = self.nodes[id as usize].take();
+        self.free.push(id);
+        node.unwrap_or_else(|| unreachable!("released LruDict node {id} twice"))
+    }
+
+    fn unlink(&mut self, id: NodeId) {
+        let (prev, next) = {
+            let node = self.node_mut(id);
+            (node.prev.take(), node.next.take())
+        };
+        match prev {
+            Some(p) => self.node_mut(p).next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(n) => self.node_mut(n).prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    fn push_front(&mut self, id: NodeId) {
+        let old_head = self.head;
+        {
+            let node = self.node_mut(id);
+            node.prev = None;
+            node.next = old_head;
+        }
+        match old_head {
+            Some(h) => self.node_mut(h).prev = Some(id),
+            None => self.tail = Some(id),
+        }
+        self.head = Some(id);
+    }
+}
+
+/// INDEX
+impl<K, V> LruDict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    fn bucket_of(&self, hash: u128) -> usize {
+        (hash % self.buckets.len() as u128) as usize
+    }
+
+    fn link_bucket(&mut self, id: NodeId) {
+        let bucket = self.bucket_of(self.node(id).hash);
+        let head = self.buckets[bucket].replace(id);
+        self.node_mut(id).bucket_next = head;
+    }
+
+    // doubles the buckets and relinks every node by its stored hash
+    fn grow_index(&mut self) {
+        let len = (self.buckets.len() * 2).max(MIN_BUCKETS);
+        self.buckets = vec![None; len];
+        for id in 0..self.nodes.len() as NodeId {
+            if self.nodes[id as usize].is_some() {
+                self.link_bucket(id);
+            }
+        }
+    }
+}
+
+/// Borrowing iterator from the most to the least recently used entry
+pub struct LruIter<'a, K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    dict: &'a LruDict<K, V>,
+    next: Option<NodeId>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for LruIter<'a, K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.dict.node(self.next?);
+        self.next = node.next;
+        self.remaining -= 1;
+        Some((&node.key, &node.val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for LruIter<'_, K, V> where K: PartialOrd + Serialize {}
+
+impl<K, V> Debug for LruDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,118 @@
+// Combining and reconciling HashDicts
+//
+// `merge_with` folds one dict into another, asking a closure what to do when
+// both hold a key. `diff` describes how to turn one dict into another, and
+// `apply_diff` replays that description, so a replica only has to be sent
+// what changed. The diff serializes like any other serde type.
+
+use std::{cmp::Ordering, fmt::Debug};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Bucket, HashDict};
+
+/// What it takes to turn one `HashDict` into another, see `HashDict::diff`.
+/// Every list is sorted by key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HashDictDiff<K, V> {
+    /// Entries only the other dict has
+    pub added: Vec<(K, V)>,
+    /// Keys only this dict has
+    pub removed: Vec<K>,
+    /// Keys both have, with the other dict's differing value
+    pub changed: Vec<(K, V)>,
+}
+
+impl<K, V> HashDictDiff<K, V> {
+    /// True when the two dicts held the same entries
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Number of keys that differ
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.changed.len()
+    }
+}
+
+impl<K, V> Default for HashDictDiff<K, V> {
+    fn default() -> Self {
+        HashDictDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl<K, V, B> HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    /// Moves every entry of `other` into this dict. When both hold a key,
+    /// `resolve(existing, incoming)` decides what the value becomes.
+    ///
+    /// `other` can use a different bucket type, bucket count and seed.
+    pub fn merge_with<B2, F>(&mut self, other: HashDict<K, V, B2>, mut resolve: F) -> &mut Self
+    where
+        B2: Bucket<K, V>,
+        F: FnMut(&mut V, V),
+    {
+        for mut bucket in other.table {
+            for (key, incoming) in bucket.extract_if(|_, _| true) {
+                match self.get_mut(&key) {
+                    Some(existing) => resolve(existing, incoming),
+                    None => {
+                        self.insert(key, incoming);
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Describes what `apply_diff` has to do to this dict to make it equal
+    /// to `other`
+    pub fn diff<B2>(&self, other: &HashDict<K, V, B2>) -> HashDictDiff<K, V>
+    where
+        K: Clone,
+        V: Clone + PartialEq,
+        B2: Bucket<K, V>,
+    {
+        let mut diff = HashDictDiff::default();
+        for (k, v) in other.entries() {
+            match self.get_ref(k) {
+                None => diff.added.push((k.clone(), v.clone())),
+                Some(mine) if mine != v => diff.changed.push((k.clone(), v.clone())),
+                Some(_) => {}
+            }
+        }
+        diff.removed = self
+            .entries()
+            .filter(|(k, _)| !other.contains_key(*k))
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        diff.added.sort_by(|a, b| by_key(&a.0, &b.0));
+        diff.changed.sort_by(|a, b| by_key(&a.0, &b.0));
+        diff.removed.sort_by(by_key);
+        diff
+    }
+
+    /// Patches this dict with a diff made by `diff`
+    pub fn apply_diff(&mut self, diff: HashDictDiff<K, V>) -> &mut Self {
+        for key in diff.removed.iter() {
+            self.delete(key);
+        }
+        for (key, value) in diff.added.into_iter().chain(diff.changed) {
+            self.insert(key, value);
+        }
+        self
+    }
+}
+
+fn by_key<K: PartialOrd>(a: &K, b: &K) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
@@ -0,0 +1,201 @@
+// Multi-map on top of HashDict
+//
+// A HashDict holds one value per key, inserting the key again replaces it.
+// HashMultiDict keeps every value instead: each key maps to a VecDeque of
+// its values in the order they were inserted, so adding a value and taking
+// the oldest one off are both O(1).
+
+use std::{
+    borrow::Borrow,
+    collections::VecDeque,
+    fmt::{self, Debug},
+    iter::FromIterator,
+};
+
+use serde::Serialize;
+
+use crate::{Bucket, HashDict, RbTree};
+
+/// # Hash Multi Dictionary
+///
+/// types: K, V and B, the kind of bucket as for `HashDict`.
+///
+/// public functions:
+/// - new
+/// - with_bucket_type
+/// - insert_multi
+/// - get_all
+/// - count
+/// - remove_one
+/// - remove_all
+/// - contains_key
+/// - len
+/// - key_count
+#[derive(Clone)]
+pub struct HashMultiDict<K, V, B = RbTree<K, VecDeque<V>>>
+where
+    K: PartialOrd + Serialize,
+    V: Debug,
+{
+    dict: HashDict<K, VecDeque<V>, B>,
+    values: usize,
+}
+
+impl<K, V> HashMultiDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    /// Creates a table of `len` `RbTree` buckets
+    pub fn new(len: usize, seed: u32) -> Self {
+        Self::with_bucket_type(len, seed)
+    }
+}
+
+impl<K, V, B> HashMultiDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, VecDeque<V>>,
+{
+    pub fn with_bucket_type(len: usize, seed: u32) -> Self {
+        HashMultiDict {
+            dict: HashDict::with_bucket_type(len, seed),
+            values: 0,
+        }
+    }
+
+    /// Adds a value under `key`, after the values already there
+    pub fn insert_multi(&mut self, key: K, value: V) -> &mut Self {
+        match self.dict.get_mut(&key) {
+            Some(values) => values.push_back(value),
+            None => {
+                self.dict.insert(key, VecDeque::from([value]));
+            }
+        }
+        self.values += 1;
+        self
+    }
+
+    /// Every value stored under `key`, oldest first
+    pub fn get_all<Q>(&self, key: &Q) -> impl DoubleEndedIterator<Item = &V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.get_ref(key).into_iter().flatten()
+    }
+
+    /// Number of values stored under `key`
+    pub fn count<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.get_ref(key).map_or(0, VecDeque::len)
+    }
+
+    /// Removes the oldest value stored under `key`, the key goes away
+    /// with its last value
+    pub fn remove_one<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let values = self.dict.get_mut(key)?;
+        let value = values.pop_front()?;
+        if values.is_empty() {
+            self.dict.delete(key);
+        }
+        self.values -= 1;
+        Some(value)
+    }
+
+    /// Removes the key and hands back all of its values, oldest first
+    pub fn remove_all<Q>(&mut self, key: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let values = self.dict.pop(key).unwrap_or_default();
+        self.values -= values.len();
+        values.into()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.contains_key(key)
+    }
+
+    /// Number of values, counting every value of every key
+    pub fn len(&self) -> usize {
+        self.values
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values == 0
+    }
+
+    /// Number of distinct keys
+    pub fn key_count(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.dict.seed()
+    }
+}
+
+impl<K, V, B> Default for HashMultiDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, VecDeque<V>>,
+{
+    fn default() -> Self {
+        HashMultiDict {
+            dict: HashDict::default(),
+            values: 0,
+        }
+    }
+}
+
+impl<K, V, B> FromIterator<(K, V)> for HashMultiDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, VecDeque<V>>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut dict = HashMultiDict::default();
+        dict.extend(iter);
+        dict
+    }
+}
+
+impl<K, V, B> Extend<(K, V)> for HashMultiDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, VecDeque<V>>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert_multi(k, v);
+        }
+    }
+}
+
+impl<K, V, B> Debug for HashMultiDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Debug + Clone,
+    B: Bucket<K, VecDeque<V>>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.dict, f)
+    }
+}
@@ -2,135 +2,182 @@
 // chatgpt help
 // and https://github.com/judwhite/Grassfed.MurmurHash3/blob/master/Grassfed.MurmurHash3/MurmurHash3.cs
 
-use bincode;
 use byteorder::{ByteOrder, LittleEndian};
 use serde::Serialize;
 use std::io::Result;
 
-pub fn hash_anything<T: Serialize>(item: &T, seed: u32) -> Result<u128> {
+pub fn hash_anything<T: Serialize + ?Sized>(item: &T, seed: u32) -> Result<u128> {
     let bytes = bincode::serialize(item).expect("Can't convert this item");
     hash_mumur3(bytes, seed)
 }
 
 pub fn hash_mumur3<T: AsRef<[u8]>>(key: T, seed: u32) -> Result<u128> {
-    const C1: u64 = 0x87c37b91114253d5;
-    const C2: u64 = 0x4cf5ad432745937f;
-
-    let mut h_seed1 = seed as u64;
-    let mut h_seed2 = seed as u64;
-
-    let key_data = key.as_ref();
-    let chunk_size = 16;
-
-    let mut num_bytes = 0;
-    for chunk in key_data.chunks(chunk_size) {
-        if chunk.len() == chunk_size {
-            // converting to little endian to convert to x64 architecture
-            let mut k1 = LittleEndian::read_u64(&chunk[0..8]);
-            let mut k2 = LittleEndian::read_u64(&chunk[8..chunk.len()]);
-
-            // Mix k1
-            k1 = k1.wrapping_mul(C1);
-            k1 = k1.rotate_left(31);
-            k1 = k1.wrapping_mul(C2);
-            h_seed1 ^= k1;
-            h_seed1 = h_seed1.rotate_left(27);
-            h_seed1 = h_seed1.wrapping_add(h_seed2);
-            h_seed1 = h_seed1.wrapping_mul(5).wrapping_add(0x52dce729);
-
-            // Mix k2
+    let mut hasher = Murmur3::new(seed);
+    hasher.write(key.as_ref());
+    Ok(hasher.finish())
+}
+
+const C1: u64 = 0x87c37b91114253d5;
+const C2: u64 = 0x4cf5ad432745937f;
+const CHUNK_SIZE: usize = 16;
+
+// murmur3 fed in pieces, hashing them as if they were one key, so callers
+// can hash several slices without copying them together first
+pub(crate) struct Murmur3 {
+    h_seed1: u64,
+    h_seed2: u64,
+    num_bytes: usize,
+    // start of a chunk that isn't complete yet
+    pending: [u8; CHUNK_SIZE],
+    pending_len: usize,
+}
+
+impl Murmur3 {
+    pub(crate) fn new(seed: u32) -> Self {
+        Murmur3 {
+            h_seed1: seed as u64,
+            h_seed2: seed as u64,
+            num_bytes: 0,
+            pending: [0; CHUNK_SIZE],
+            pending_len: 0,
+        }
+    }
+
+    pub(crate) fn write(&mut self, mut bytes: &[u8]) {
+        self.num_bytes += bytes.len();
+        if self.pending_len > 0 {
+            let take = (CHUNK_SIZE - self.pending_len).min(bytes.len());
+            self.pending[self.pending_len..self.pending_len + take].copy_from_slice(&bytes[..take]);
+            self.pending_len += take;
+            bytes = &bytes[take..];
+            if self.pending_len < CHUNK_SIZE {
+                return;
+            }
+            let chunk = self.pending;
+            self.mix_chunk(&chunk);
+            self.pending_len = 0;
+        }
+
+        let mut chunks = bytes.chunks_exact(CHUNK_SIZE);
+        for chunk in &mut chunks {
+            self.mix_chunk(chunk);
+        }
+        let tail = chunks.remainder();
+        self.pending[..tail.len()].copy_from_slice(tail);
+        self.pending_len = tail.len();
+    }
+
+    pub(crate) fn finish(mut self) -> u128 {
+        if self.pending_len > 0 {
+            let tail = self.pending;
+            self.mix_tail(&tail[..self.pending_len]);
+        }
+        let (mut h_seed1, mut h_seed2) = (self.h_seed1, self.h_seed2);
+
+        // final mixes, these also run when the key length is a multiple of
+        // 16 and there is no tail chunk
+        h_seed1 ^= self.num_bytes as u64;
+        h_seed2 ^= self.num_bytes as u64;
+
+        h_seed1 = h_seed1.wrapping_add(h_seed2);
+        h_seed2 = h_seed2.wrapping_add(h_seed1);
+
+        h_seed1 = fmix64(h_seed1);
+        h_seed2 = fmix64(h_seed2);
+
+        h_seed1 = h_seed1.wrapping_add(h_seed2);
+        h_seed2 = h_seed2.wrapping_add(h_seed1);
+
+        ((h_seed2 as u128) << 64) | (h_seed1 as u128)
+    }
+
+    fn mix_chunk(&mut self, chunk: &[u8]) {
+        // converting to little endian to convert to x64 architecture
+        let mut k1 = LittleEndian::read_u64(&chunk[0..8]);
+        let mut k2 = LittleEndian::read_u64(&chunk[8..CHUNK_SIZE]);
+
+        // Mix k1
+        k1 = k1.wrapping_mul(C1);
+        k1 = k1.rotate_left(31);
+        k1 = k1.wrapping_mul(C2);
+        self.h_seed1 ^= k1;
+        self.h_seed1 = self.h_seed1.rotate_left(27);
+        self.h_seed1 = self.h_seed1.wrapping_add(self.h_seed2);
+        self.h_seed1 = self.h_seed1.wrapping_mul(5).wrapping_add(0x52dce729);
+
+        // Mix k2
+        k2 = k2.wrapping_mul(C2);
+        k2 = k2.rotate_left(33);
+        k2 = k2.wrapping_mul(C1);
+        self.h_seed2 ^= k2;
+        self.h_seed2 = self.h_seed2.rotate_left(31);
+        self.h_seed2 = self.h_seed2.wrapping_add(self.h_seed1);
+        self.h_seed2 = self.h_seed2.wrapping_mul(5).wrapping_add(0x38495ab5);
+    }
+
+    // the last 1 to 15 bytes
+    fn mix_tail(&mut self, chunk: &[u8]) {
+        let remaining = chunk.len();
+        let mut k1: u64 = 0;
+        let mut k2: u64 = 0;
+
+        if remaining >= 15 {
+            k2 ^= (chunk[14] as u64) << 48;
+        }
+        if remaining >= 14 {
+            k2 ^= (chunk[13] as u64) << 40;
+        }
+        if remaining >= 13 {
+            k2 ^= (chunk[12] as u64) << 32;
+        }
+        if remaining >= 12 {
+            k2 ^= (chunk[11] as u64) << 24;
+        }
+        if remaining >= 11 {
+            k2 ^= (chunk[10] as u64) << 16;
+        }
+        if remaining >= 10 {
+            k2 ^= (chunk[9] as u64) << 8;
+        }
+        if remaining >= 9 {
+            k2 ^= chunk[8] as u64;
             k2 = k2.wrapping_mul(C2);
             k2 = k2.rotate_left(33);
             k2 = k2.wrapping_mul(C1);
-            h_seed2 ^= k2;
-            h_seed2 = h_seed2.rotate_left(31);
-            h_seed2 = h_seed2.wrapping_add(h_seed1);
-            h_seed2 = h_seed2.wrapping_mul(5).wrapping_add(0x38495ab5);
+            self.h_seed2 ^= k2;
+        }
 
-            num_bytes += 16;
+        if remaining >= 8 {
+            k1 ^= LittleEndian::read_u64(&chunk[0..8]);
         } else {
-            // tail
-
-            let remaining = chunk.len();
-            num_bytes += remaining;
-            let mut k1: u64 = 0;
-            let mut k2: u64 = 0;
-
-            if remaining >= 15 {
-                k2 ^= (chunk[14] as u64) << 48;
-            }
-            if remaining >= 14 {
-                k2 ^= (chunk[13] as u64) << 40;
+            if remaining >= 7 {
+                k1 ^= (chunk[6] as u64) << 48;
             }
-            if remaining >= 13 {
-                k2 ^= (chunk[12] as u64) << 32;
+            if remaining >= 6 {
+                k1 ^= (chunk[5] as u64) << 40;
             }
-            if remaining >= 12 {
-                k2 ^= (chunk[11] as u64) << 24;
+            if remaining >= 5 {
+                k1 ^= (chunk[4] as u64) << 32;
             }
-            if remaining >= 11 {
-                k2 ^= (chunk[10] as u64) << 16;
+            if remaining >= 4 {
+                k1 ^= (chunk[3] as u64) << 24;
             }
-            if remaining >= 10 {
-                k2 ^= (chunk[9] as u64) << 8;
+            if remaining >= 3 {
+                k1 ^= (chunk[2] as u64) << 16;
             }
-            if remaining >= 9 {
-                k2 ^= chunk[8] as u64;
-                k2 = k2.wrapping_mul(C2);
-                k2 = k2.rotate_left(33);
-                k2 = k2.wrapping_mul(C1);
-                h_seed2 ^= k2;
+            if remaining >= 2 {
+                k1 ^= (chunk[1] as u64) << 8;
             }
-
-            if remaining >= 8 {
-                k1 ^= LittleEndian::read_u64(&chunk[0..8]);
-            } else {
-                if remaining >= 7 {
-                    k1 ^= (chunk[6] as u64) << 48;
-                }
-                if remaining >= 6 {
-                    k1 ^= (chunk[5] as u64) << 40;
-                }
-                if remaining >= 5 {
-                    k1 ^= (chunk[4] as u64) << 32;
-                }
-                if remaining >= 4 {
-                    k1 ^= (chunk[3] as u64) << 24;
-                }
-                if remaining >= 3 {
-                    k1 ^= (chunk[2] as u64) << 16;
-                }
-                if remaining >= 2 {
-                    k1 ^= (chunk[1] as u64) << 8;
-                }
-                if remaining >= 1 {
-                    k1 ^= chunk[0] as u64;
-                }
+            if remaining >= 1 {
+                k1 ^= chunk[0] as u64;
             }
-
-            k1 = k1.wrapping_mul(C1);
-            k1 = k1.rotate_left(31);
-            k1 = k1.wrapping_mul(C2);
-            h_seed1 ^= k1;
-
-            // final mixes
-            h_seed1 ^= num_bytes as u64;
-            h_seed2 ^= num_bytes as u64;
-
-            h_seed1 = h_seed1.wrapping_add(h_seed2);
-            h_seed2 = h_seed2.wrapping_add(h_seed1);
-
-            h_seed1 = fmix64(h_seed1);
-            h_seed2 = fmix64(h_seed2);
-
-            h_seed1 = h_seed1.wrapping_add(h_seed2);
-            h_seed2 = h_seed2.wrapping_add(h_seed1);
-
-            let x = ((h_seed2 as u128) << 64) | (h_seed1 as u128);
-            return Ok(x);
         }
+
+        k1 = k1.wrapping_mul(C1);
+        k1 = k1.rotate_left(31);
+        k1 = k1.wrapping_mul(C2);
+        self.h_seed1 ^= k1;
     }
-    Ok(1 as u128)
 }
 
 fn fmix64(mut k: u64) -> u64 {
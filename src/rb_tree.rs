@@ -1,170 +1,316 @@
-// use std::borrow::Borrow;
-use std::{
-    cell::RefCell,
-    fmt::{self, Debug},
-    rc::Rc,
-};
+use std::{borrow::Borrow, fmt::Debug, mem, vec};
 
-use PartialEq;
+use crate::{RbIter, RbRefIter};
 
-use crate::RbIter;
+use super::{Color, NodeId, RbNode, RbTree, Slot};
 
-use super::{Color, Ptr, RbNode, RbTree};
-
-impl<K: PartialOrd, V: PartialEq + Debug> RbNode<K, V> {
-    pub fn new(key: K, val: V) -> Option<Ptr<K, V>> {
-        Some(Rc::new(RefCell::new(RbNode {
+impl<K: PartialOrd, V: Debug> RbNode<K, V> {
+    pub fn new(key: K, val: V, parent: Option<NodeId>) -> Self {
+        RbNode {
             key,
             val,
-            parent: None,
+            parent,
             left_child: None,
             right_child: None,
             color: Color::Red,
-        })))
+        }
     }
 }
 /// PUBLIC HELPERS
+impl<K, V> Default for RbTree<K, V>
+where
+    K: PartialOrd + Debug,
+    V: Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K, V> RbTree<K, V>
 where
     K: PartialOrd + Debug,
-    V: PartialEq + PartialOrd + Debug,
+    V: Debug,
 {
     pub fn new() -> Self {
         RbTree {
+            nodes: Vec::new(),
+            free: None,
             root: None,
             length: 0,
         }
     }
 
     pub fn insert(&mut self, key: K, value: V) -> &mut Self {
-        let new_node = RbNode::new(key, value).expect("Failed to create a new node");
-
-        let mut res_node = None;
-        let mut i = self.root.clone();
-
-        while let Some(ref curr_node) = i.clone() {
-            res_node = Some(curr_node.clone());
-            if new_node.borrow().key < curr_node.borrow().key {
-                i = curr_node.borrow().left_child.clone();
+        let mut parent = None;
+        let mut i = self.root;
+
+        while let Some(curr_node) = i {
+            parent = Some(curr_node);
+            let curr_node = self.node(curr_node);
+            i = if key < curr_node.key {
+                curr_node.left_child
             } else {
-                i = curr_node.borrow().right_child.clone();
-            }
+                curr_node.right_child
+            };
         }
 
-        new_node.borrow_mut().parent = res_node.clone();
+        let goes_left = parent.is_some_and(|p| key < self.node(p).key);
+        let new_node = self.alloc(RbNode::new(key, value, parent));
 
-        if let Some(parent) = res_node.clone() {
-            if new_node.borrow().key < parent.borrow().key {
-                parent.borrow_mut().left_child = Some(new_node.clone());
-            } else {
-                parent.borrow_mut().right_child = Some(new_node.clone());
-            }
-            new_node.borrow_mut().parent = Some(parent);
-        } else {
-            self.root = Some(new_node.clone());
+        match parent {
+            Some(p) if goes_left => self.node_mut(p).left_child = Some(new_node),
+            Some(p) => self.node_mut(p).right_child = Some(new_node),
+            None => self.root = Some(new_node),
         }
 
         self.in_fix_up(new_node);
 
         self.length += 1;
-        return self;
+        self
     }
 
     pub fn delete(&mut self, key: K) -> &mut Self {
-        if let Some(ref z) = self.search(&key) {
-            let mut y = z.clone();
-            let x;
-            let mut y_og_color: Color = Self::node_color(&Some(y.clone()));
-
-            if z.borrow().left_child.is_none() {
-                x = z.borrow().right_child.clone();
-                self.rb_transplant(z.clone(), x.clone());
-            } else if z.borrow().right_child.is_none() {
-                x = z.borrow().left_child.clone();
-                self.rb_transplant(z.clone(), x.clone());
-            } else {
-                y = self.find_min(z.borrow().right_child.clone().expect("right must exist")); // this finds right most (which is a leaf)
-                y_og_color = Self::node_color(&Some(y.clone()));
-                x = y.borrow().right_child.clone(); // this breaks if right child is none
-
-                if y.borrow()
-                    .parent
-                    .as_ref()
-                    .map_or(false, |p| Rc::ptr_eq(p, z))
-                {
-                    if let Some(ref x) = x.clone() {
-                        x.borrow_mut().parent = Some(z.clone());
-                    }
-                } else {
-                    self.rb_transplant(y.clone(), x.clone());
-                    y.borrow_mut().right_child = z.borrow().right_child.clone();
-                    if let Some(ref right) = y.borrow().right_child {
-                        right.borrow_mut().parent = Some(y.clone());
-                    }
-                }
+        self.remove_entry(&key);
+        self
+    }
 
-                self.rb_transplant(z.clone(), Some(y.clone()));
-                y.borrow_mut().left_child = z.borrow().left_child.clone();
-                if let Some(ref left) = y.borrow().left_child {
-                    left.borrow_mut().parent = Some(y.clone());
-                }
-                y.borrow_mut().color = z.borrow().color;
-            }
+    /// Deletes a node matching the borrowed form of a key and hands back
+    /// the key and value that were stored in it
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let z = self.search(key)?;
+        Some(self.remove_node(z))
+    }
 
-            if y_og_color == Color::Black {
-                // println!(
-                //     "Before delete_fixup:\n x = {:?},\n\nroot = {:?},\n\n x color = {:?}",
-                //     x,
-                //     self.root,
-                //     Self::node_color(&x)
-                // );
-                self.delete_fixup(x.clone());
-            }
+    /// Borrows the entry with the smallest key
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let n = self.node(self.find_min(self.root?));
+        Some((&n.key, &n.val))
+    }
 
-            self.length -= 1;
-        }
+    /// Deletes the entry with the smallest key and hands it back. Among
+    /// equal keys, the one inserted first goes first.
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let min = self.find_min(self.root?);
+        Some(self.remove_node(min))
+    }
 
-        return self;
+    /// Borrows the entry with the smallest key not below `key`, in
+    /// O(log n). Among equal keys, the one inserted first.
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut best = None;
+        let mut curr_node = self.root;
+        while let Some(id) = curr_node {
+            let n = self.node(id);
+            if n.key.borrow() < key {
+                curr_node = n.right_child;
+            } else {
+                best = Some(n);
+                curr_node = n.left_child;
+            }
+        }
+        best.map(|n| (&n.key, &n.val))
     }
 
     pub fn key_exist(&self, key: K) -> bool {
         self.search(&key).is_some()
     }
 
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.search(key).is_some()
+    }
+
+    /// Drops every entry and hands the arena's memory back
     pub fn clear(&mut self) -> &mut Self {
+        self.nodes = Vec::new();
+        self.free = None;
         self.root = None;
         self.length = 0;
         self
     }
 
     /// This gets you an immutable value
-    pub fn get(&self, key: &K) -> Option<V>
+    ///
+    /// `key` may be any borrowed form of `K` (e.g. `&str` for `String` keys)
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
     where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
         V: Clone,
     {
-        match self.search(key) {
-            Some(n) => Some(n.borrow().val.clone()),
-            None => None,
-        }
+        self.get_ref(key).cloned()
     }
 
-    /// This is a function callbacl that allows you
-    /// to mutable modify Value V
-    pub fn get_mut<F>(&mut self, key: &K, mut f: F)
+    /// This is a function callback that allows you
+    /// to mutably modify Value V
+    pub fn update<Q, F>(&mut self, key: &Q, mut f: F)
     where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
         F: FnMut(&mut V),
     {
-        match self.search(key) {
-            Some(n) => {
-                f(&mut n.borrow_mut().val);
-            }
-            None => (),
+        if let Some(v) = self.get_mut(key) {
+            f(v);
         }
     }
 
     pub fn len(&self) -> i32 {
         self.length
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Number of nodes on the longest path from the root down, 0 when empty
+    pub fn height(&self) -> usize {
+        // level-order walk, one level at a time
+        let mut level: Vec<_> = self.root.into_iter().collect();
+        let mut height = 0;
+        while !level.is_empty() {
+            height += 1;
+            level = level
+                .iter()
+                .flat_map(|&id| {
+                    let node = self.node(id);
+                    [node.left_child, node.right_child]
+                })
+                .flatten()
+                .collect();
+        }
+        height
+    }
+
+    /// Number of nodes the arena can hold before it has to grow
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Moves every entry to the front of the arena and releases the unused
+    /// slots, for trees that shrank a lot and are not expected to grow back
+    pub fn shrink_to_fit(&mut self) {
+        if self.free.is_some() {
+            let mut old = mem::take(&mut self.nodes);
+            self.nodes.reserve_exact(self.length as usize);
+            self.root = Self::move_subtree(&mut old, &mut self.nodes, self.root, None);
+            self.free = None;
+        }
+        self.nodes.shrink_to_fit();
+    }
+
+    /// Borrows the value stored under `key` without cloning it
+    pub fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.search(key).map(|n| &self.node(n).val)
+    }
+
+    /// Borrows the stored key along with its value
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        self.search(key).map(|n| {
+            let n = self.node(n);
+            (&n.key, &n.val)
+        })
+    }
+
+    /// Mutably borrows the value stored under `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let n = self.search(key)?;
+        Some(&mut self.node_mut(n).val)
+    }
+}
+
+/// ARENA
+impl<K: PartialOrd, V: Debug> RbTree<K, V> {
+    fn node(&self, id: NodeId) -> &RbNode<K, V> {
+        match &self.nodes[id as usize] {
+            Slot::Occupied(node) => node,
+            Slot::Vacant(_) => unreachable!("freed node {id} is still linked into the tree"),
+        }
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut RbNode<K, V> {
+        match &mut self.nodes[id as usize] {
+            Slot::Occupied(node) => node,
+            Slot::Vacant(_) => unreachable!("freed node {id} is still linked into the tree"),
+        }
+    }
+
+    /// Stores a node in the first free slot, growing the arena if there is none
+    fn alloc(&mut self, node: RbNode<K, V>) -> NodeId {
+        match self.free {
+            Some(id) => {
+                let slot = mem::replace(&mut self.nodes[id as usize], Slot::Occupied(node));
+                self.free = match slot {
+                    Slot::Vacant(next) => next,
+                    Slot::Occupied(_) => unreachable!("free list points at a live node"),
+                };
+                id
+            }
+            None => {
+                self.nodes.push(Slot::Occupied(node));
+                (self.nodes.len() - 1) as NodeId
+            }
+        }
+    }
+
+    /// Moves the subtree under `id` from `old` into `new` in pre-order,
+    /// relinking it by the new indices
+    fn move_subtree(
+        old: &mut [Slot<K, V>],
+        new: &mut Vec<Slot<K, V>>,
+        id: Option<NodeId>,
+        parent: Option<NodeId>,
+    ) -> Option<NodeId> {
+        let mut node = match mem::replace(&mut old[id? as usize], Slot::Vacant(None)) {
+            Slot::Occupied(node) => node,
+            Slot::Vacant(_) => unreachable!("freed node is still linked into the tree"),
+        };
+        let (left, right) = (node.left_child, node.right_child);
+        node.parent = parent;
+        let new_id = new.len() as NodeId;
+        new.push(Slot::Occupied(node));
+
+        let left = Self::move_subtree(old, new, left, Some(new_id));
+        let right = Self::move_subtree(old, new, right, Some(new_id));
+        if let Slot::Occupied(node) = &mut new[new_id as usize] {
+            node.left_child = left;
+            node.right_child = right;
+        }
+        Some(new_id)
+    }
+
+    /// Takes a node out of the arena and puts its slot on the free list
+    fn release(&mut self, id: NodeId) -> RbNode<K, V> {
+        let slot = mem::replace(&mut self.nodes[id as usize], Slot::Vacant(self.free));
+        self.free = Some(id);
+        match slot {
+            Slot::Occupied(node) => node,
+            Slot::Vacant(_) => unreachable!("node {id} was released twice"),
+        }
+    }
 }
 
 /// PRIVATE HELPERS
@@ -172,372 +318,307 @@ impl<K: PartialOrd + Debug, V: Debug> RbTree<K, V> {
     // Helper functions below
 
     //search
-    fn search(&self, k: &K) -> Option<Ptr<K, V>> {
-        let mut curr_node = self.root.clone();
-        while let Some(ref n) = curr_node.clone() {
-            if k < &n.borrow().key {
-                curr_node = n.borrow().left_child.clone();
-            } else if k > &n.borrow().key {
-                curr_node = n.borrow().right_child.clone();
+    fn search<Q>(&self, k: &Q) -> Option<NodeId>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mut curr_node = self.root;
+        while let Some(id) = curr_node {
+            let n = self.node(id);
+            if k < n.key.borrow() {
+                curr_node = n.left_child;
+            } else if k > n.key.borrow() {
+                curr_node = n.right_child;
             } else {
-                return Some(n.clone());
+                return Some(id);
             }
         }
-        return None;
+        None
     }
 
     /// Rotates tree to the left
-    fn left_rotate(&mut self, x: Ptr<K, V>) {
-        let y = x.borrow().right_child.as_ref().unwrap().clone();
-
-        let y_temp = y.borrow_mut().left_child.take();
-        x.borrow_mut().right_child = y_temp.clone();
+    fn left_rotate(&mut self, x: NodeId) {
+        let y = self.node(x).right_child.expect("right child must exist");
 
-        if let Some(ref y_left) = y_temp {
-            y_left.borrow_mut().parent = Some(x.clone());
+        let y_left = self.node(y).left_child;
+        self.node_mut(x).right_child = y_left;
+        if let Some(y_left) = y_left {
+            self.node_mut(y_left).parent = Some(x);
         }
 
-        y.borrow_mut().parent = x.borrow_mut().parent.clone();
-        if x.borrow().parent.is_none() {
-            self.root = Some(y.clone());
-        } else {
-            let x_left_is_child = {
-                let x_parent = x.borrow().parent.as_ref().unwrap().clone();
-                let x_parent_borrow = x_parent.borrow();
-                if let Some(ref parent_left) = x_parent_borrow.left_child {
-                    Rc::ptr_eq(parent_left, &x)
-                } else {
-                    false
-                }
-            };
-
-            let x_parent = x.borrow().parent.as_ref().unwrap().clone();
-            if x_left_is_child {
-                x_parent.borrow_mut().left_child = Some(y.clone());
-            } else {
-                x_parent.borrow_mut().right_child = Some(y.clone());
-            }
+        let x_parent = self.node(x).parent;
+        self.node_mut(y).parent = x_parent;
+        match x_parent {
+            None => self.root = Some(y),
+            Some(p) if self.node(p).left_child == Some(x) => self.node_mut(p).left_child = Some(y),
+            Some(p) => self.node_mut(p).right_child = Some(y),
         }
-        y.borrow_mut().left_child = Some(x.clone());
-        x.borrow_mut().parent = Some(y.clone());
+
+        self.node_mut(y).left_child = Some(x);
+        self.node_mut(x).parent = Some(y);
     }
 
     /// Rotates tree to the right
-    fn right_rotate(&mut self, x: Ptr<K, V>) {
-        let y = x.borrow().left_child.as_ref().unwrap().clone();
+    fn right_rotate(&mut self, x: NodeId) {
+        let y = self.node(x).left_child.expect("left child must exist");
 
-        let y_temp = y.borrow_mut().right_child.take();
-        x.borrow_mut().left_child = y_temp.clone();
-
-        if let Some(y_right) = y_temp {
-            y_right.borrow_mut().parent = Some(x.clone());
+        let y_right = self.node(y).right_child;
+        self.node_mut(x).left_child = y_right;
+        if let Some(y_right) = y_right {
+            self.node_mut(y_right).parent = Some(x);
         }
 
-        y.borrow_mut().parent = x.borrow_mut().parent.clone();
-        if x.borrow().parent.is_none() {
-            self.root = Some(y.clone());
-        } else {
-            let x_right_is_child = {
-                let x_parent = x.borrow().parent.as_ref().unwrap().clone();
-                let x_parent_borrow = x_parent.borrow();
-                if let Some(ref parent_right) = x_parent_borrow.right_child {
-                    Rc::ptr_eq(parent_right, &x)
-                } else {
-                    false
-                }
-            };
-
-            let x_parent = x.borrow().parent.as_ref().unwrap().clone();
-            if x_right_is_child {
-                x_parent.borrow_mut().right_child = Some(y.clone());
-            } else {
-                x_parent.borrow_mut().left_child = Some(y.clone());
+        let x_parent = self.node(x).parent;
+        self.node_mut(y).parent = x_parent;
+        match x_parent {
+            None => self.root = Some(y),
+            Some(p) if self.node(p).right_child == Some(x) => {
+                self.node_mut(p).right_child = Some(y)
             }
+            Some(p) => self.node_mut(p).left_child = Some(y),
         }
 
-        y.borrow_mut().right_child = Some(x.clone());
-        x.borrow_mut().parent = Some(y.clone());
+        self.node_mut(y).right_child = Some(x);
+        self.node_mut(x).parent = Some(y);
     }
 
     /// This function fixes up the rb-tree for insertions
-    ///
-    /// It looks very disgusting but that is what I get
-    /// for using safe rust
-    fn in_fix_up(&mut self, mut z: Ptr<K, V>) {
-        while z
-            .borrow()
+    fn in_fix_up(&mut self, mut z: NodeId) {
+        while let Some(zp) = self
+            .node(z)
             .parent
-            .as_ref()
-            .map_or(false, |p| p.borrow().color == Color::Red)
+            .filter(|&p| self.node(p).color == Color::Red)
         {
-            let zp = z.borrow().parent.as_ref().unwrap().clone(); // parent
-            if zp.borrow().parent.is_none() {
+            let Some(zpp) = self.node(zp).parent else {
                 // if the node z is the child of the root node, because the root
                 // node does not have a parent, and thus the "grandparent" would not exist
                 break;
-            }
-
-            let zpp = zp.borrow().parent.as_ref().unwrap().clone(); // grandparent
+            };
 
-            // if Rc::ptr_eq(&zp, &zpp.borrow().left_child.as_ref().unwrap()) {
-            if zpp
-                .borrow()
-                .left_child
-                .as_ref()
-                .map_or(false, |left| Rc::ptr_eq(&zp, left))
-            {
+            if self.node(zpp).left_child == Some(zp) {
                 //z.parent is the left child
-                let y = zpp.borrow().right_child.clone();
+                let y = self.node(zpp).right_child;
 
-                if let Some(y) = y.as_ref().filter(|y| y.borrow().color == Color::Red) {
+                if self.node_color(y) == Color::Red {
                     // case 1
-                    zp.borrow_mut().color = Color::Black;
-                    y.borrow_mut().color = Color::Black;
-                    zpp.borrow_mut().color = Color::Red;
-                    z = zpp.clone();
+                    self.set_color(Some(zp), Color::Black);
+                    self.set_color(y, Color::Black);
+                    self.set_color(Some(zpp), Color::Red);
+                    z = zpp;
                 } else {
                     // case 2
-                    if zp
-                        .borrow()
-                        .right_child
-                        .as_ref()
-                        .map_or(false, |right| Rc::ptr_eq(&z, right))
-                    {
-                        z = zp.clone();
-                        self.left_rotate(z.clone());
+                    if self.node(zp).right_child == Some(z) {
+                        z = zp;
+                        self.left_rotate(z);
                     }
 
                     // case 3
-                    zp.borrow_mut().color = Color::Black;
-                    zpp.borrow_mut().color = Color::Red;
-                    self.right_rotate(zpp.clone());
+                    // after a case 2 rotation z's parent is a different node
+                    let zp = self.node(z).parent;
+                    self.set_color(zp, Color::Black);
+                    self.set_color(Some(zpp), Color::Red);
+                    self.right_rotate(zpp);
                 }
             } else {
-                let y = zpp.borrow().left_child.clone();
+                let y = self.node(zpp).left_child;
 
-                if let Some(y) = y.as_ref().filter(|y| y.borrow().color == Color::Red) {
+                if self.node_color(y) == Color::Red {
                     // case 1
-                    zp.borrow_mut().color = Color::Black;
-                    y.borrow_mut().color = Color::Black;
-                    zpp.borrow_mut().color = Color::Red;
-                    z = zpp.clone();
+                    self.set_color(Some(zp), Color::Black);
+                    self.set_color(y, Color::Black);
+                    self.set_color(Some(zpp), Color::Red);
+                    z = zpp;
                 } else {
                     // case 2
-                    if zp
-                        .borrow()
-                        .left_child
-                        .as_ref()
-                        .map_or(false, |left| Rc::ptr_eq(&z, left))
-                    {
-                        z = zp.clone();
-                        self.right_rotate(z.clone());
+                    if self.node(zp).left_child == Some(z) {
+                        z = zp;
+                        self.right_rotate(z);
                     }
 
                     //case 3
-                    zp.borrow_mut().color = Color::Black;
-                    zpp.borrow_mut().color = Color::Red;
+                    let zp = self.node(z).parent;
+                    self.set_color(zp, Color::Black);
+                    self.set_color(Some(zpp), Color::Red);
                     self.left_rotate(zpp);
                 }
             }
         }
 
-        if let Some(ref root) = self.root {
-            root.borrow_mut().color = Color::Black;
-        }
+        self.set_color(self.root, Color::Black);
     }
 
     // Let's start implementing delete helpers
-    fn rb_transplant(&mut self, u: Ptr<K, V>, v: Option<Ptr<K, V>>) {
-        let u_parent = u.borrow().parent.clone();
+    fn rb_transplant(&mut self, u: NodeId, v: Option<NodeId>) {
+        let u_parent = self.node(u).parent;
 
         match u_parent {
-            Some(ref parent) => {
-                if parent
-                    .borrow()
-                    .left_child
-                    .as_ref()
-                    .map_or(false, |left| Rc::ptr_eq(&u, &left))
-                {
-                    parent.borrow_mut().left_child = v.clone()
-                } else {
-                    parent.borrow_mut().right_child = v.clone();
-                }
+            Some(parent) if self.node(parent).left_child == Some(u) => {
+                self.node_mut(parent).left_child = v
             }
-            None => self.root = v.clone(),
+            Some(parent) => self.node_mut(parent).right_child = v,
+            None => self.root = v,
         }
 
-        // v.borrow_mut().parent = u.borrow().parent.clone();
-        if let Some(ref v_inner) = v {
-            v_inner.borrow_mut().parent = u.borrow().parent.clone();
+        if let Some(v) = v {
+            self.node_mut(v).parent = u_parent;
         }
     }
 
-    fn find_min(&mut self, x: Ptr<K, V>) -> Ptr<K, V> {
-        let mut current = x;
-        while let Some(ref left) = current.clone().borrow().left_child {
-            current = left.clone();
+    /// CLRS RB-DELETE of node z, returning what it held
+    fn remove_node(&mut self, z: NodeId) -> (K, V) {
+        let mut y_og_color = self.node_color(Some(z));
+        let x;
+        // x can be None, so its parent has to be tracked separately
+        let x_parent;
+
+        let (z_left, z_right) = (self.node(z).left_child, self.node(z).right_child);
+        if z_left.is_none() {
+            x = z_right;
+            x_parent = self.node(z).parent;
+            self.rb_transplant(z, x);
+        } else if z_right.is_none() {
+            x = z_left;
+            x_parent = self.node(z).parent;
+            self.rb_transplant(z, x);
+        } else {
+            let y = self.find_min(z_right.expect("right must exist"));
+            y_og_color = self.node_color(Some(y));
+            x = self.node(y).right_child;
+
+            if self.node(y).parent == Some(z) {
+                x_parent = Some(y);
+            } else {
+                x_parent = self.node(y).parent;
+                self.rb_transplant(y, x);
+                self.node_mut(y).right_child = z_right;
+                if let Some(right) = z_right {
+                    self.node_mut(right).parent = Some(y);
+                }
+            }
+
+            self.rb_transplant(z, Some(y));
+            self.node_mut(y).left_child = z_left;
+            if let Some(left) = z_left {
+                self.node_mut(left).parent = Some(y);
+            }
+            self.node_mut(y).color = self.node(z).color;
         }
 
-        return current;
-    }
-}
+        if y_og_color == Color::Black {
+            self.delete_fixup(x, x_parent);
+        }
 
-impl<K, V> RbTree<K, V>
-where
-    K: PartialOrd + Debug,
-    V: PartialEq + Debug,
-{
-    /// get node color
-    fn node_color(node: &Option<Ptr<K, V>>) -> Color {
-        node.as_ref().map_or(Color::Black, |n| n.borrow().color)
+        self.length -= 1;
+        let node = self.release(z);
+        if self.length == 0 {
+            // nothing left to link to, so the free list can go too
+            self.nodes.clear();
+            self.free = None;
+            self.root = None;
+        }
+        (node.key, node.val)
     }
 
-    /// check if left child
-    fn is_left_child(node: &Ptr<K, V>) -> bool {
-        match node.borrow().parent {
-            Some(ref parent) => parent
-                .borrow()
-                .left_child
-                .as_ref()
-                .map_or(false, |l_child| Rc::ptr_eq(l_child, node)),
-            None => false,
+    fn find_min(&self, x: NodeId) -> NodeId {
+        let mut current = x;
+        while let Some(left) = self.node(current).left_child {
+            current = left;
         }
-    }
 
-    /// retrieve sibling
-    fn sibling(node: &Ptr<K, V>) -> Option<Ptr<K, V>> {
-        node.borrow().parent.as_ref().and_then(|p| {
-            if Self::is_left_child(node) {
-                p.borrow().right_child.clone()
-            } else {
-                p.borrow().left_child.clone()
-            }
-        })
+        current
     }
 
-    #[allow(dead_code)]
-    fn replace_node(node: &Ptr<K, V>, replacement: Option<Ptr<K, V>>) {
-        if let Some(ref parent) = node.borrow().parent {
-            if Self::is_left_child(node) {
-                parent.borrow_mut().left_child = replacement.clone();
-            } else {
-                parent.borrow_mut().right_child = replacement.clone();
-            }
-        }
-        if let Some(ref replacement) = replacement {
-            replacement.borrow_mut().parent = node.borrow().parent.clone();
-        }
+    /// get node color, missing leaves are black
+    fn node_color(&self, node: Option<NodeId>) -> Color {
+        node.map_or(Color::Black, |n| self.node(n).color)
     }
 
     /// set's the color of a node
-    fn set_color(node: &Option<Ptr<K, V>>, color: Color) {
-        if let Some(ref n) = node {
-            n.borrow_mut().color = color;
+    fn set_color(&mut self, node: Option<NodeId>, color: Color) {
+        if let Some(n) = node {
+            self.node_mut(n).color = color;
         }
     }
 
-    fn delete_fixup(&mut self, mut x: Option<Ptr<K, V>>) {
-        // check if pointers have the same location
-        while !x.as_ref().map_or(false, |x_ref| {
-            Rc::ptr_eq(x_ref, &self.root.clone().unwrap())
-        }) && Self::node_color(&x) == Color::Black
-        {
-            if let Some(ref x_unwrapped) = x {
-                if Self::is_left_child(x_unwrapped) {
-                    let mut w = Self::sibling(x_unwrapped).expect("Sibling must exist");
-                    if Self::node_color(&Some(w.clone())) == Color::Red {
-                        // case 1
-                        Self::set_color(&Some(w.clone()), Color::Black);
-                        Self::set_color(&x_unwrapped.borrow().parent, Color::Red);
-                        self.left_rotate(
-                            x_unwrapped
-                                .borrow()
-                                .parent
-                                .clone()
-                                .expect("parent must exist"),
-                        );
-                        let new_w = Self::sibling(x_unwrapped).expect("sibling must exist");
-                        w = new_w;
-                    }
+    /// Restores the red-black properties after a delete.
+    ///
+    /// `x` is the node that moved into the deleted spot and may be None,
+    /// which is why its parent is passed in rather than read off of it.
+    fn delete_fixup(&mut self, mut x: Option<NodeId>, mut parent: Option<NodeId>) {
+        while let Some(p) = parent {
+            if self.node_color(x) == Color::Red {
+                break;
+            }
 
+            if self.node(p).left_child == x {
+                let mut w = self.node(p).right_child.expect("sibling must exist");
+                if self.node_color(Some(w)) == Color::Red {
+                    // case 1
+                    self.set_color(Some(w), Color::Black);
+                    self.set_color(Some(p), Color::Red);
+                    self.left_rotate(p);
+                    w = self.node(p).right_child.expect("sibling must exist");
+                }
+
+                if self.node_color(self.node(w).left_child) == Color::Black
+                    && self.node_color(self.node(w).right_child) == Color::Black
+                {
                     // case 2
-                    if Self::node_color(&w.borrow().left_child) == Color::Black
-                        && Self::node_color(&w.borrow().right_child) == Color::Black
-                    {
-                        Self::set_color(&Some(w.clone()), Color::Red);
-                        let x_new = x_unwrapped.borrow().parent.clone();
-                        x = x_new;
-                    } else {
-                        if Self::node_color(&w.borrow().right_child) == Color::Black {
-                            Self::set_color(&w.borrow().left_child, Color::Black);
-                            Self::set_color(&Some(w.clone()), Color::Red);
-                            self.right_rotate(w.clone());
-                            let new_w = Self::sibling(x_unwrapped).expect("Sibling must exist");
-                            w = new_w;
-                        }
-
-                        Self::set_color(
-                            &Some(w.clone()),
-                            Self::node_color(&x_unwrapped.borrow().parent),
-                        );
-                        Self::set_color(&x_unwrapped.borrow().parent, Color::Black);
-                        Self::set_color(&w.borrow().right_child, Color::Black);
-                        self.left_rotate(
-                            x_unwrapped
-                                .borrow()
-                                .parent
-                                .clone()
-                                .expect("parent must exist"),
-                        );
-
-                        let x_new = self.root.clone();
-                        x = x_new;
-                    }
+                    self.set_color(Some(w), Color::Red);
+                    parent = self.node(p).parent;
+                    x = Some(p);
                 } else {
-                    // Mirror image of the above code with left and right swapped.
-                    let mut w = Self::sibling(x_unwrapped).expect("Sibling must exist");
-                    if Self::node_color(&Some(w.clone())) == Color::Red {
-                        Self::set_color(&Some(w.clone()), Color::Black);
-                        Self::set_color(&x_unwrapped.borrow().parent, Color::Red);
-                        self.right_rotate(x_unwrapped.borrow().parent.clone().unwrap());
-                        let new_w = Self::sibling(x_unwrapped)
-                            .expect("New sibling must exist after rotation");
-                        w = new_w;
+                    if self.node_color(self.node(w).right_child) == Color::Black {
+                        // case 3
+                        self.set_color(self.node(w).left_child, Color::Black);
+                        self.set_color(Some(w), Color::Red);
+                        self.right_rotate(w);
+                        w = self.node(p).right_child.expect("sibling must exist");
                     }
 
-                    if Self::node_color(&w.borrow().right_child) == Color::Black
-                        && Self::node_color(&w.borrow().left_child) == Color::Black
-                    {
-                        Self::set_color(&Some(w.clone()), Color::Red);
-                        let new_x = x_unwrapped.borrow().parent.clone();
-                        x = new_x;
-                    } else {
-                        if Self::node_color(&w.borrow().left_child) == Color::Black {
-                            Self::set_color(&w.borrow().right_child, Color::Black);
-                            Self::set_color(&Some(w.clone()), Color::Red);
-                            self.left_rotate(w.clone());
-                            let new_w = Self::sibling(x_unwrapped)
-                                .expect("New sibling must exist after rotation");
-                            w = new_w;
-                        }
-
-                        Self::set_color(
-                            &Some(w.clone()),
-                            Self::node_color(&x_unwrapped.borrow().parent),
-                        );
-                        Self::set_color(&x_unwrapped.borrow().parent, Color::Black);
-                        Self::set_color(&w.borrow().left_child, Color::Black);
-                        self.right_rotate(x_unwrapped.borrow().parent.clone().unwrap());
-                        x = self.root.clone();
-                    }
+                    // case 4
+                    self.set_color(Some(w), self.node_color(Some(p)));
+                    self.set_color(Some(p), Color::Black);
+                    self.set_color(self.node(w).right_child, Color::Black);
+                    self.left_rotate(p);
+                    x = self.root;
+                    break;
                 }
             } else {
-                break;
+                // Mirror image of the above code with left and right swapped.
+                let mut w = self.node(p).left_child.expect("sibling must exist");
+                if self.node_color(Some(w)) == Color::Red {
+                    self.set_color(Some(w), Color::Black);
+                    self.set_color(Some(p), Color::Red);
+                    self.right_rotate(p);
+                    w = self.node(p).left_child.expect("sibling must exist");
+                }
+
+                if self.node_color(self.node(w).right_child) == Color::Black
+                    && self.node_color(self.node(w).left_child) == Color::Black
+                {
+                    self.set_color(Some(w), Color::Red);
+                    parent = self.node(p).parent;
+                    x = Some(p);
+                } else {
+                    if self.node_color(self.node(w).left_child) == Color::Black {
+                        self.set_color(self.node(w).right_child, Color::Black);
+                        self.set_color(Some(w), Color::Red);
+                        self.left_rotate(w);
+                        w = self.node(p).left_child.expect("sibling must exist");
+                    }
+
+                    self.set_color(Some(w), self.node_color(Some(p)));
+                    self.set_color(Some(p), Color::Black);
+                    self.set_color(self.node(w).left_child, Color::Black);
+                    self.right_rotate(p);
+                    x = self.root;
+                    break;
+                }
             }
         }
-        if let Some(ref x) = x {
-            Self::set_color(&Some(x.clone()), Color::Black);
-        }
+        self.set_color(x, Color::Black);
     }
 }
 
@@ -548,56 +629,27 @@ where
     V: std::fmt::Debug,
 {
     pub fn print_in_order(&self) {
-        fn in_order<K, V>(node: &Option<Rc<RefCell<RbNode<K, V>>>>)
-        where
-            K: PartialOrd + std::fmt::Debug,
-            V: std::fmt::Debug,
-        {
-            if let Some(ref n) = node {
-                in_order(&n.borrow().left_child);
-                println!("Key: {:?}, Value: {:?}", n.borrow().key, n.borrow().val);
-                in_order(&n.borrow().right_child);
-            }
-        }
-
         println!("In-order Traversal:");
-        in_order(&self.root);
-    }
-}
-
-impl<K: Debug + PartialOrd, V: Debug> fmt::Debug for RbNode<K, V> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_struct("RbNode")
-            .field("key", &self.key)
-            .field("color", &self.color)
-            // Omit the parent to prevent cyclic printing
-            .field("left_child", &self.left_child)
-            .field("right_child", &self.right_child)
-            .finish()
+        for (k, v) in self.iter_ref() {
+            println!("Key: {:?}, Value: {:?}", k, v);
+        }
     }
 }
 
 impl<K: std::fmt::Debug + PartialOrd, V: std::fmt::Debug> RbTree<K, V> {
     pub fn print_tree(&self) {
-        self.print_node(&self.root, 0);
+        self.print_node(self.root, 0);
     }
 
-    fn print_node(&self, node: &Option<Ptr<K, V>>, depth: usize) {
+    fn print_node(&self, node: Option<NodeId>, depth: usize) {
         if let Some(n) = node {
+            let n = self.node(n);
             let indent = "->".repeat(depth); // Create indentation based on the depth
-            println!("{}|{:?}|", indent, n.borrow().key); // Print the current node
-
-            // Recursively print the left child, if it exists
-            if n.borrow().left_child.is_some() {
-                // println!("{}left:", indent); // Label for left child
-                self.print_node(&n.borrow().left_child, depth + 1); // Recursion with increased depth
-            }
+            println!("{}|{:?}|", indent, n.key); // Print the current node
 
-            // Recursively print the right child, if it exists
-            if n.borrow().right_child.is_some() {
-                // println!("{}right:", indent); // Label for right child
-                self.print_node(&n.borrow().right_child, depth + 1); // Recursion with increased depth
-            }
+            // Recursively print the children, if they exist
+            self.print_node(n.left_child, depth + 1);
+            self.print_node(n.right_child, depth + 1);
         }
     }
 }
@@ -608,18 +660,18 @@ where
     V: Debug,
 {
     pub fn print_ascii_tree(&self) {
-        Self::print_ascii(&self.root, 0, 0, false);
+        self.print_ascii(self.root, 0, 0, false);
     }
-    fn print_ascii(node: &Option<Ptr<K, V>>, space: usize, depth: usize, is_left: bool) {
-        if let Some(node) = node.clone() {
-            let borrowed_node = node.borrow();
-            let color = match borrowed_node.color {
+    fn print_ascii(&self, node: Option<NodeId>, space: usize, depth: usize, is_left: bool) {
+        if let Some(node) = node {
+            let node = self.node(node);
+            let color = match node.color {
                 Color::Black => "B",
                 Color::Red => "R",
             };
             let offset = 5;
 
-            Self::print_ascii(&borrowed_node.left_child, space + offset, depth + 1, true);
+            self.print_ascii(node.left_child, space + offset, depth + 1, true);
 
             let indent = " ".repeat(space);
             if depth != 0 {
@@ -628,14 +680,14 @@ where
                     "{}{}{:?}({})",
                     indent,
                     if is_left { "┌──" } else { "└──" },
-                    borrowed_node.key,
+                    node.key,
                     color
                 );
             } else {
-                println!("{}{:?}({})", indent, borrowed_node.key, color);
+                println!("{}{:?}({})", indent, node.key, color);
             }
 
-            Self::print_ascii(&borrowed_node.right_child, space + offset, depth + 1, false);
+            self.print_ascii(node.right_child, space + offset, depth + 1, false);
         }
     }
 }
@@ -650,35 +702,208 @@ where
     type Item = (K, V);
 
     fn next(&mut self) -> Option<Self::Item> {
-        return self.iter.next();
+        self.iter.next()
     }
 }
 
 impl<K, V> RbTree<K, V>
 where
     K: PartialEq + PartialOrd + Debug + Clone,
-    V: PartialEq + Debug + Clone,
+    V: Debug + Clone,
 {
-    fn inorder_stack_recurse(&self, node: &Option<Ptr<K, V>>, vec_in: &mut Vec<(K, V)>) {
-        if let Some(ref n) = node.clone() {
-            self.inorder_stack_recurse(&n.borrow().left_child, vec_in);
+    /// Iterates over clones of the entries in key order
+    pub fn iter(&self) -> RbIter<K, V> {
+        let stack: Vec<(K, V)> = self
+            .iter_ref()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        RbIter {
+            iter: stack.into_iter(),
+        }
+    }
+}
+
+impl<K: PartialOrd, V: Debug> RbTree<K, V> {
+    /// Iterates over the entries in key order without cloning them
+    pub fn iter_ref(&self) -> RbRefIter<'_, K, V> {
+        let mut iter = RbRefIter {
+            tree: self,
+            stack: Vec::new(),
+            remaining: self.length as usize,
+        };
+        iter.push_left(self.root);
+        iter
+    }
+
+    /// Takes the tree apart into its entries in key order
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let order = self.in_order_ids();
+        let mut nodes = self.nodes;
+        order
+            .into_iter()
+            .map(
+                |id| match mem::replace(&mut nodes[id as usize], Slot::Vacant(None)) {
+                    Slot::Occupied(node) => (node.key, node.val),
+                    Slot::Vacant(_) => unreachable!("freed node is still linked into the tree"),
+                },
+            )
+            .collect()
+    }
+
+    /// Builds a tree out of entries already sorted by key in O(n), rather
+    /// than inserting them one at a time
+    pub fn from_sorted_vec(entries: Vec<(K, V)>) -> Self {
+        let len = entries.len();
+        let mut tree = RbTree {
+            nodes: Vec::with_capacity(len),
+            free: None,
+            root: None,
+            length: len as i32,
+        };
+        // Halving the range at every level leaves every missing child at
+        // one of two depths. Unless the tree is perfect, the nodes on the
+        // deepest level are colored red and everything else black, which
+        // gives every path the same number of black nodes.
+        let red_depth = (!(len + 1).is_power_of_two()).then(|| (len + 1).ilog2() as usize);
+        tree.root = tree.build_sorted(&mut entries.into_iter(), len, 0, red_depth);
+        tree
+    }
 
-            vec_in.push((n.borrow().key.clone(), n.borrow().val.clone()));
+    /// Takes out the entries `f` returns true for, in key order. The tree
+    /// is rebuilt once from the entries left, instead of deleting them one
+    /// at a time.
+    pub fn extract_if<F>(&mut self, mut f: F) -> Vec<(K, V)>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let marked: Vec<bool> = self
+            .in_order_ids()
+            .into_iter()
+            .map(|id| {
+                let node = self.node_mut(id);
+                f(&node.key, &mut node.val)
+            })
+            .collect();
+        if !marked.contains(&true) {
+            return Vec::new();
+        }
 
-            self.inorder_stack_recurse(&n.borrow().right_child, vec_in);
+        let entries = mem::replace(self, RbTree::from_sorted_vec(Vec::new())).into_sorted_vec();
+        let mut kept = Vec::with_capacity(entries.len());
+        let mut extracted = Vec::new();
+        for (entry, extract) in entries.into_iter().zip(marked) {
+            if extract {
+                extracted.push(entry);
+            } else {
+                kept.push(entry);
+            }
         }
+        *self = RbTree::from_sorted_vec(kept);
+        extracted
     }
 
-    fn inorder_items(&self) -> Vec<(K, V)> {
-        let mut stack: Vec<(K, V)> = Vec::new();
-        self.inorder_stack_recurse(&self.root, &mut stack);
-        return stack;
+    /// Keeps only the entries `f` returns true for
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.extract_if(|k, v| !f(k, v));
     }
 
-    pub fn iter(&self) -> RbIter<K, V> {
-        let stack = self.inorder_items();
-        return RbIter {
-            iter: stack.into_iter(),
+    fn in_order_ids(&self) -> Vec<NodeId> {
+        let mut order = Vec::with_capacity(self.length as usize);
+        let mut stack = Vec::new();
+        let mut node = self.root;
+        while node.is_some() || !stack.is_empty() {
+            while let Some(id) = node {
+                stack.push(id);
+                node = self.node(id).left_child;
+            }
+            let id = stack.pop().expect("stack is not empty");
+            order.push(id);
+            node = self.node(id).right_child;
+        }
+        order
+    }
+
+    // builds the subtree of the next `len` entries, in order
+    fn build_sorted(
+        &mut self,
+        entries: &mut vec::IntoIter<(K, V)>,
+        len: usize,
+        depth: usize,
+        red_depth: Option<usize>,
+    ) -> Option<NodeId> {
+        if len == 0 {
+            return None;
+        }
+        let left_len = (len - 1) / 2;
+        let left = self.build_sorted(entries, left_len, depth + 1, red_depth);
+        let (key, val) = entries.next().expect("fewer entries than counted");
+        let id = self.alloc(RbNode::new(key, val, None));
+        let right = self.build_sorted(entries, len - 1 - left_len, depth + 1, red_depth);
+
+        for child in [left, right].into_iter().flatten() {
+            self.node_mut(child).parent = Some(id);
+        }
+        let node = self.node_mut(id);
+        node.left_child = left;
+        node.right_child = right;
+        node.color = if red_depth == Some(depth) {
+            Color::Red
+        } else {
+            Color::Black
         };
+        Some(id)
+    }
+}
+
+impl<K: PartialOrd, V: Debug> IntoIterator for RbTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_sorted_vec().into_iter()
+    }
+}
+
+impl<K: PartialOrd, V: Debug> RbRefIter<'_, K, V> {
+    // queues `node` and its chain of left children, smallest on top
+    fn push_left(&mut self, mut node: Option<NodeId>) {
+        while let Some(id) = node {
+            self.stack.push(id);
+            node = self.tree.node(id).left_child;
+        }
+    }
+}
+
+impl<'a, K: PartialOrd, V: Debug> Iterator for RbRefIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.stack.pop()?;
+        let node = self.tree.node(id);
+        self.push_left(node.right_child);
+        self.remaining -= 1;
+        Some((&node.key, &node.val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: PartialOrd, V: Debug> ExactSizeIterator for RbRefIter<'_, K, V> {}
+
+impl<'a, K, V> IntoIterator for &'a RbTree<K, V>
+where
+    K: PartialOrd,
+    V: Debug,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = RbRefIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_ref()
     }
 }
@@ -0,0 +1,174 @@
+// Rendezvous, or highest random weight, hashing
+//
+// Every member scores every key, and the key goes to the highest score.
+// The score of a member with weight w is w / -ln(u), u being the murmur3
+// hash of the key's and the member's hashes mapped into (0, 1), so members
+// win keys in proportion to their weight. Removing a member only moves the
+// keys it won, and adding one only takes keys for itself.
+//
+// Routing costs O(members) but needs no ring, and `route_n` gives the next
+// best members too, e.g. for replicas.
+
+use std::fmt::{self, Debug};
+
+use serde::Serialize;
+
+use crate::{hash_anything, hash_mumur3};
+
+struct Member<N> {
+    node: N,
+    weight: f64,
+    // murmur3 of the node, worked out once
+    hash: u64,
+}
+
+/// # Rendezvous Hash
+///
+/// Weighted highest random weight hashing of keys onto members.
+///
+/// types: N, the member
+///
+/// public functions:
+/// - new
+/// - add
+/// - add_weighted
+/// - remove
+/// - route
+/// - route_n
+/// - weight
+/// - members
+/// - contains
+/// - len
+/// - seed
+pub struct RendezvousHash<N>
+where
+    N: PartialEq + Serialize,
+{
+    members: Vec<Member<N>>,
+    seed: u32,
+}
+
+impl<N> RendezvousHash<N>
+where
+    N: PartialEq + Serialize,
+{
+    pub fn new(seed: u32) -> Self {
+        RendezvousHash {
+            members: Vec::new(),
+            seed,
+        }
+    }
+
+    /// Adds `node` with weight 1, false if it was already a member
+    pub fn add(&mut self, node: N) -> bool {
+        self.add_weighted(node, 1.0)
+    }
+
+    /// Adds `node` to win about `weight` times the keys of a weight 1
+    /// member, false if it was already a member
+    ///
+    /// Panics if `weight` isn't a positive finite number
+    pub fn add_weighted(&mut self, node: N, weight: f64) -> bool {
+        assert!(
+            weight.is_finite() && weight > 0.0,
+            "member weight must be positive, got {weight}"
+        );
+        if self.contains(&node) {
+            return false;
+        }
+        let Ok(hash) = hash_anything(&node, self.seed) else {
+            return false;
+        };
+        self.members.push(Member {
+            node,
+            weight,
+            hash: hash as u64,
+        });
+        true
+    }
+
+    /// Takes `node` out, false if it wasn't a member
+    pub fn remove(&mut self, node: &N) -> bool {
+        let Some(i) = self.members.iter().position(|m| m.node == *node) else {
+            return false;
+        };
+        self.members.remove(i);
+        true
+    }
+
+    /// The member `key` belongs to, `None` without members
+    pub fn route<Q: Serialize + ?Sized>(&self, key: &Q) -> Option<&N> {
+        let key = hash_anything(key, self.seed).ok()? as u64;
+        self.members
+            .iter()
+            .map(|m| (self.score(key, m), &m.node))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, node)| node)
+    }
+
+    /// The `n` best members for `key`, best first. When the first leaves,
+    /// the key moves to the second, and so on.
+    pub fn route_n<Q: Serialize + ?Sized>(&self, key: &Q, n: usize) -> Vec<&N> {
+        let Ok(key) = hash_anything(key, self.seed) else {
+            return Vec::new();
+        };
+        let mut scored: Vec<(f64, &N)> = self
+            .members
+            .iter()
+            .map(|m| (self.score(key as u64, m), &m.node))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(n).map(|(_, node)| node).collect()
+    }
+
+    /// Weight of `node`, `None` if it isn't a member
+    pub fn weight(&self, node: &N) -> Option<f64> {
+        self.members
+            .iter()
+            .find(|m| m.node == *node)
+            .map(|m| m.weight)
+    }
+
+    pub fn contains(&self, node: &N) -> bool {
+        self.members.iter().any(|m| m.node == *node)
+    }
+
+    /// The members, in the order they were added
+    pub fn members(&self) -> impl Iterator<Item = &N> {
+        self.members.iter().map(|m| &m.node)
+    }
+
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn score(&self, key: u64, member: &Member<N>) -> f64 {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&key.to_le_bytes());
+        bytes[8..].copy_from_slice(&member.hash.to_le_bytes());
+        let hash = hash_mumur3(bytes, self.seed).map_or(0, |h| h as u64);
+        // top 53 bits, centered so u is never 0 or 1
+        let u = ((hash >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+        member.weight / -u.ln()
+    }
+}
+
+impl<N> Debug for RendezvousHash<N>
+where
+    N: PartialEq + Serialize + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.members.iter().map(|m| (&m.node, m.weight)))
+            .finish()
+    }
+}
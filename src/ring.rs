@@ -0,0 +1,137 @@
+// Consistent hashing ring on murmur3
+//
+// Every member is placed on a ring of u64 positions `vnodes` times, at the
+// murmur3 hash of (member, replica). A key is routed to the first position
+// at or after its own hash, wrapping around past the end. Adding a member
+// only takes over the arcs in front of its new positions, and removing one
+// only hands its arcs to the next positions, so about 1/n of the keys move
+// either way. More virtual nodes per member even out the arc lengths.
+//
+// The positions sit in an RbTree keyed by position, so routing is a
+// logarithmic `ceiling` lookup.
+
+use std::fmt::{self, Debug};
+
+use serde::Serialize;
+
+use crate::{hash_anything, RbTree};
+
+/// # Hash Ring
+///
+/// Consistent hashing of keys onto a changing set of members.
+///
+/// types: N, the member, e.g. a node name or address
+///
+/// public functions:
+/// - new
+/// - add
+/// - remove
+/// - route
+/// - contains
+/// - members
+/// - len
+/// - vnodes
+/// - seed
+#[derive(Clone)]
+pub struct HashRing<N>
+where
+    N: PartialOrd + Serialize + Debug + Clone,
+{
+    ring: RbTree<u64, N>,
+    members: Vec<N>,
+    vnodes: usize,
+    seed: u32,
+}
+
+impl<N> HashRing<N>
+where
+    N: PartialOrd + Serialize + Debug + Clone,
+{
+    /// Creates an empty ring placing every member `vnodes` times
+    pub fn new(vnodes: usize, seed: u32) -> Self {
+        HashRing {
+            ring: RbTree::new(),
+            members: Vec::new(),
+            vnodes: vnodes.max(1),
+            seed,
+        }
+    }
+
+    /// Places `node` on the ring, false if it was already a member
+    pub fn add(&mut self, node: N) -> bool {
+        if self.contains(&node) {
+            return false;
+        }
+        for replica in 0..self.vnodes {
+            if let Some(pos) = position(&(&node, replica), self.seed) {
+                self.ring.insert(pos, node.clone());
+            }
+        }
+        self.members.push(node);
+        true
+    }
+
+    /// Takes `node` off the ring, false if it wasn't a member
+    pub fn remove(&mut self, node: &N) -> bool {
+        let Some(i) = self.members.iter().position(|m| m == node) else {
+            return false;
+        };
+        self.members.remove(i);
+        self.ring.retain(|_, n| n != node);
+        true
+    }
+
+    /// The member `key` belongs to, `None` on an empty ring
+    pub fn route<Q: Serialize + ?Sized>(&self, key: &Q) -> Option<&N> {
+        let pos = position(key, self.seed)?;
+        self.ring
+            .ceiling(&pos)
+            .or_else(|| self.ring.first())
+            .map(|(_, node)| node)
+    }
+
+    pub fn contains(&self, node: &N) -> bool {
+        self.members.contains(node)
+    }
+
+    /// The members, in the order they were added
+    pub fn members(&self) -> &[N] {
+        &self.members
+    }
+
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Positions every member takes on the ring
+    pub fn vnodes(&self) -> usize {
+        self.vnodes
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
+// where a key or a virtual node sits on the ring
+fn position<T: Serialize + ?Sized>(item: &T, seed: u32) -> Option<u64> {
+    hash_anything(item, seed).ok().map(|hash| hash as u64)
+}
+
+impl<N> Debug for HashRing<N>
+where
+    N: PartialOrd + Serialize + Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashRing")
+            .field("members", &self.members)
+            .field("vnodes", &self.vnodes)
+            .field("seed", &self.seed)
+            .finish()
+    }
+}
@@ -0,0 +1,468 @@
+// Open-addressing HashDict with Robin Hood displacement
+//
+// Entries live directly in one flat slot array instead of per-bucket trees,
+// so a lookup is usually a single cache line. A key starts probing at its
+// home slot (murmur3 hash & mask) and walks right. On insert, an entry
+// that is further from home than the one sitting in a slot takes that
+// slot and the displaced entry keeps walking ("steal from the rich"),
+// which keeps every probe sequence short and lets a lookup stop as soon
+// as it meets an entry closer to home than itself.
+//
+// Deletion shifts the following entries of the run back one slot instead
+// of leaving a tombstone behind, so there are no tombstones to skip over
+// or clean up, and the table never needs rehashing because of deletes.
+
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    iter::FromIterator,
+    mem,
+    ops::Index,
+};
+
+use serde::Serialize;
+
+use crate::{
+    dict::{DEFAULT_LEN, DEFAULT_SEED},
+    hash_anything, Dict, HashDictIter,
+};
+
+const MIN_CAPACITY: usize = 8;
+// the table grows past 7/8 full
+const MAX_LOAD_NUM: usize = 7;
+const MAX_LOAD_DEN: usize = 8;
+
+#[derive(Clone)]
+struct Slot<K, V> {
+    hash: u64,
+    key: K,
+    val: V,
+}
+
+/// # Robin Hood Dictionary
+///
+/// Open-addressing alternative to `HashDict` with the same API, hashed with
+/// the same murmur3 function. It grows by doubling, so unlike `HashDict` it
+/// never degrades when more entries arrive than it was sized for.
+///
+/// public functions:
+/// - new
+/// - insert
+/// - get
+/// - get_ref
+/// - get_mut
+/// - get_key_value
+/// - contains_key
+/// - update
+/// - delete
+/// - pop
+/// - remove_entry
+/// - len
+/// - capacity
+/// - max_probe_len
+/// - seed
+/// - iter
+#[derive(Clone)]
+pub struct RobinHoodDict<K, V> {
+    seed: u32,
+    len: usize,
+    slots: Vec<Option<Slot<K, V>>>,
+}
+
+impl<K, V> RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    /// Creates a table with room for `len` entries before it has to grow
+    pub fn new(len: usize, seed: u32) -> Self {
+        let mut dict = RobinHoodDict {
+            seed,
+            len: 0,
+            slots: Vec::new(),
+        };
+        dict.resize(Self::capacity_for(len));
+        dict
+    }
+
+    /// Inserts a key-value pair, replacing the value if the key is
+    /// already in the table
+    pub fn insert(&mut self, key: K, value: V) -> &mut Self {
+        // like HashDict, a key that fails to serialize has no hash and
+        // is not inserted
+        let Some(hash) = self.hash(&key) else {
+            return self;
+        };
+        if let Some(i) = self.find(hash, &key) {
+            if let Some(slot) = &mut self.slots[i] {
+                slot.val = value;
+            }
+            return self;
+        }
+
+        if (self.len + 1) * MAX_LOAD_DEN > self.slots.len() * MAX_LOAD_NUM {
+            self.resize(Self::capacity_for(self.len + 1).max(self.slots.len() * 2));
+        }
+        self.place(Slot {
+            hash,
+            key,
+            val: value,
+        });
+        self.len += 1;
+        self
+    }
+
+    /// Returns a clone of the value stored under `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        V: Clone,
+    {
+        self.get_ref(key).cloned()
+    }
+
+    pub fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let i = self.find(self.hash(key)?, key)?;
+        self.slots[i].as_mut().map(|slot| &mut slot.val)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let i = self.find(self.hash(key)?, key)?;
+        self.slots[i].as_ref().map(|slot| (&slot.key, &slot.val))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Runs `f` on the value stored under `key`, if there is one
+    pub fn update<Q, F>(&mut self, key: &Q, mut f: F)
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        F: FnMut(&mut V),
+    {
+        if let Some(v) = self.get_mut(key) {
+            f(v);
+        }
+    }
+
+    pub fn delete<Q>(&mut self, key: &Q) -> &mut Self
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.remove_entry(key);
+        self
+    }
+
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let mut hole = self.find(self.hash(key)?, key)?;
+        let removed = self.slots[hole].take()?;
+
+        // backward shift: pull the rest of the run one slot closer to home
+        // until an empty slot or an entry already at home ends it
+        let mask = self.mask();
+        let mut next = (hole + 1) & mask;
+        while let Some(slot) = &self.slots[next] {
+            if self.probe_distance(slot.hash, next) == 0 {
+                break;
+            }
+            self.slots[hole] = self.slots[next].take();
+            hole = next;
+            next = (next + 1) & mask;
+        }
+
+        self.len -= 1;
+        Some((removed.key, removed.val))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of slots, the table grows once it is 7/8 full
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Longest distance any entry sits from its home slot, i.e. the most
+    /// extra slots a lookup has to look at
+    pub fn max_probe_len(&self) -> usize {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_ref().map(|s| self.probe_distance(s.hash, i)))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Keys that fail to serialize have no hash, `None` in that case
+    fn hash<Q>(&self, key: &Q) -> Option<u64>
+    where
+        Q: Serialize + ?Sized,
+    {
+        hash_anything(key, self.seed).ok().map(|hash| hash as u64)
+    }
+
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    fn probe_distance(&self, hash: u64, index: usize) -> usize {
+        index.wrapping_sub(hash as usize) & self.mask()
+    }
+
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + ?Sized,
+    {
+        let mask = self.mask();
+        let mut i = hash as usize & mask;
+        let mut dist = 0;
+        while let Some(slot) = &self.slots[i] {
+            // an entry closer to home than we are means our key would
+            // have taken its slot, so it isn't in the table
+            if self.probe_distance(slot.hash, i) < dist {
+                return None;
+            }
+            if slot.hash == hash && slot.key.borrow() == key {
+                return Some(i);
+            }
+            i = (i + 1) & mask;
+            dist += 1;
+        }
+        None
+    }
+
+    // the key must not be in the table and there must be a free slot
+    fn place(&mut self, mut entry: Slot<K, V>) {
+        let mask = self.mask();
+        let mut i = entry.hash as usize & mask;
+        let mut dist = 0;
+        loop {
+            let their_dist = match &self.slots[i] {
+                None => {
+                    self.slots[i] = Some(entry);
+                    return;
+                }
+                Some(slot) => self.probe_distance(slot.hash, i),
+            };
+            if their_dist < dist {
+                if let Some(slot) = &mut self.slots[i] {
+                    mem::swap(slot, &mut entry);
+                }
+                dist = their_dist;
+            }
+            i = (i + 1) & mask;
+            dist += 1;
+        }
+    }
+
+    fn resize(&mut self, capacity: usize) {
+        let mut slots = Vec::with_capacity(capacity);
+        slots.resize_with(capacity, || None);
+        let old = mem::replace(&mut self.slots, slots);
+        // hashes are stored, so growing doesn't rehash the keys
+        for slot in old.into_iter().flatten() {
+            self.place(slot);
+        }
+    }
+
+    fn capacity_for(len: usize) -> usize {
+        (len * MAX_LOAD_DEN)
+            .div_ceil(MAX_LOAD_NUM)
+            .next_power_of_two()
+            .max(MIN_CAPACITY)
+    }
+}
+
+// developing iters
+impl<K, V> RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Debug + Clone,
+{
+    /// Entries sorted by key, like `HashDict::iter`
+    pub fn iter(&self) -> HashDictIter<K, V> {
+        let mut stack: Vec<(K, V)> = self
+            .slots
+            .iter()
+            .flatten()
+            .map(|slot| (slot.key.clone(), slot.val.clone()))
+            .collect();
+        stack.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        HashDictIter {
+            iter: stack.into_iter(),
+        }
+    }
+}
+
+impl<K, V> Dict<K, V> for RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize,
+{
+    fn new(len: usize, seed: u32) -> Self {
+        RobinHoodDict::new(len, seed)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> &mut Self {
+        RobinHoodDict::insert(self, key, value)
+    }
+
+    fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        RobinHoodDict::get_key_value(self, key)
+    }
+
+    fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        RobinHoodDict::get_ref(self, key)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        RobinHoodDict::get_mut(self, key)
+    }
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        RobinHoodDict::remove_entry(self, key)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
+// std collection traits
+impl<K: PartialOrd + Serialize, V> Default for RobinHoodDict<K, V> {
+    fn default() -> Self {
+        RobinHoodDict::new(DEFAULT_LEN, DEFAULT_SEED)
+    }
+}
+
+impl<K: PartialOrd + Serialize, V> FromIterator<(K, V)> for RobinHoodDict<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut dict = RobinHoodDict::default();
+        dict.extend(iter);
+        dict
+    }
+}
+
+impl<K: PartialOrd + Serialize, V> Extend<(K, V)> for RobinHoodDict<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for &RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Clone + Debug,
+{
+    type Item = (K, V);
+    type IntoIter = HashDictIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, Q, V> Index<&Q> for RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize + Borrow<Q>,
+    Q: PartialOrd + Serialize + ?Sized,
+{
+    type Output = V;
+
+    /// Panics if the key is not in the table
+    fn index(&self, key: &Q) -> &V {
+        self.get_ref(key).expect("key not found in RobinHoodDict")
+    }
+}
+
+/// Compares the entries only, capacity and seed don't matter
+impl<K, V> PartialEq for RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize,
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .slots
+                .iter()
+                .flatten()
+                .all(|slot| other.get_ref(&slot.key) == Some(&slot.val))
+    }
+}
+
+impl<K, V> Debug for RobinHoodDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Clone + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,180 @@
+// Serialize/Deserialize for the collection types, enabled by the `serde-impls` feature.
+//
+// HashDict is written as its bucket count, seed and entries so that loading it
+// back puts every key in the same bucket it came from. RbTree and Tree are
+// written as plain sequences in key order.
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::{Error as _, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{Bucket, HashDict, RbTree, Tree};
+
+// -------------------- HASH TABLE --------------------
+
+// entries of every bucket, streamed without cloning
+struct Entries<'a, K, V, B>(&'a HashDict<K, V, B>)
+where
+    K: PartialOrd + Serialize,
+    V: Debug;
+
+impl<K, V, B> Serialize for Entries<'_, K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Serialize + Debug,
+    B: Bucket<K, V>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        self.0.try_for_each(|k, v| seq.serialize_element(&(k, v)))?;
+        seq.end()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "HashDict", bound = "")]
+struct HashDictRef<'a, K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Serialize + Debug,
+    B: Bucket<K, V>,
+{
+    arr_length: usize,
+    seed: u32,
+    entries: Entries<'a, K, V, B>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "HashDict")]
+struct HashDictRepr<K, V> {
+    arr_length: usize,
+    seed: u32,
+    entries: Vec<(K, V)>,
+}
+
+impl<K, V, B> Serialize for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Serialize + Debug,
+    B: Bucket<K, V>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.arr_length == 0 {
+            return Err(S::Error::custom("HashDict has no buckets"));
+        }
+        HashDictRef {
+            arr_length: self.arr_length,
+            seed: self.seed,
+            entries: Entries(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, K, V, B> Deserialize<'de> for HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Deserialize<'de> + Debug,
+    V: Deserialize<'de> + Debug,
+    B: Bucket<K, V>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = HashDictRepr::<K, V>::deserialize(deserializer)?;
+        if repr.arr_length == 0 {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(0),
+                &"a bucket count of at least 1",
+            ));
+        }
+
+        let mut dict = HashDict::with_bucket_type(repr.arr_length, repr.seed);
+        dict.extend(repr.entries);
+        Ok(dict)
+    }
+}
+
+// -------------------- RB TREE --------------------
+
+impl<K, V> Serialize for RbTree<K, V>
+where
+    K: PartialOrd + Serialize,
+    V: Serialize + Debug,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.length as usize))?;
+        for entry in self.iter_ref() {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for RbTree<K, V>
+where
+    K: PartialOrd + Deserialize<'de> + Debug,
+    V: Deserialize<'de> + Debug,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RbTreeVisitor<K, V>(PhantomData<(K, V)>);
+
+        impl<'de, K, V> Visitor<'de> for RbTreeVisitor<K, V>
+        where
+            K: PartialOrd + Deserialize<'de> + Debug,
+            V: Deserialize<'de> + Debug,
+        {
+            type Value = RbTree<K, V>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a sequence of key-value pairs")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut tree = RbTree::new();
+                while let Some((k, v)) = seq.next_element()? {
+                    tree.insert(k, v);
+                }
+                Ok(tree)
+            }
+        }
+
+        deserializer.deserialize_seq(RbTreeVisitor(PhantomData))
+    }
+}
+
+// -------------------- BINARY SEARCH TREE --------------------
+
+impl<T: PartialOrd + Serialize> Serialize for Tree<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.count()))?;
+        self.try_for_each(|v| seq.serialize_element(v))?;
+        seq.end()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Tree<T>
+where
+    T: PartialOrd + Clone + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // The values come in sorted, and inserting them in that order into an
+        // unbalanced tree would build a linked list. Inserting medians first
+        // gives back a balanced tree instead.
+        fn insert_balanced<T: PartialOrd + Clone>(tree: &mut Tree<T>, vals: &[T]) {
+            if vals.is_empty() {
+                return;
+            }
+            let mid = vals.len() / 2;
+            tree.insert(vals[mid].clone());
+            insert_balanced(tree, &vals[..mid]);
+            insert_balanced(tree, &vals[mid + 1..]);
+        }
+
+        let vals = Vec::<T>::deserialize(deserializer)?;
+        let mut tree = Tree::new();
+        insert_balanced(&mut tree, &vals);
+        Ok(tree)
+    }
+}
@@ -0,0 +1,194 @@
+// Count-Min sketch (Cormode & Muthukrishnan)
+//
+// `depth` rows of `width` counters, each row indexed by murmur3 under its
+// own seed. Adding an item bumps one counter per row, and its estimate is
+// the smallest of those counters. Collisions only ever add, so estimates
+// never fall below the true count, and with width = e / epsilon and
+// depth = ln(1 / delta) they overshoot by more than epsilon * total with a
+// probability of at most delta.
+//
+// With conservative update only the counters at the current minimum are
+// raised, just enough to cover the new count. That keeps the same
+// guarantee with noticeably smaller overestimates, at the price of not
+// being able to subtract.
+//
+// Counters and the total stop at u64::MAX instead of wrapping around to a
+// small count.
+
+use std::{
+    error::Error,
+    f64::consts::E,
+    fmt::{self, Debug},
+};
+
+use serde::Serialize;
+
+use crate::murmur::hash_mumur3;
+
+#[derive(Debug)]
+pub enum SketchError {
+    /// The sketches differ in width, depth or seed
+    Incompatible,
+}
+
+impl fmt::Display for SketchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SketchError::Incompatible => write!(f, "sketches differ in width, depth or seed"),
+        }
+    }
+}
+
+impl Error for SketchError {}
+
+/// # Count-Min Sketch
+///
+/// Approximate counts in fixed memory.
+///
+/// public functions:
+/// - new
+/// - with_dims
+/// - set_conservative
+/// - add
+/// - add_n
+/// - estimate
+/// - error_bound
+/// - merge
+/// - clear
+/// - total
+/// - width
+/// - depth
+/// - seed
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountMinSketch {
+    // row after row
+    counters: Vec<u64>,
+    width: usize,
+    depth: usize,
+    seed: u32,
+    total: u64,
+    conservative: bool,
+}
+
+impl CountMinSketch {
+    /// Creates a sketch whose estimates overshoot by more than
+    /// `epsilon * total` with a probability of at most `delta`
+    ///
+    /// Panics unless both are between 0 and 1
+    pub fn new(epsilon: f64, delta: f64, seed: u32) -> Self {
+        assert!(
+            epsilon > 0.0 && epsilon < 1.0 && delta > 0.0 && delta < 1.0,
+            "epsilon and delta must be between 0 and 1, got {epsilon} and {delta}"
+        );
+        let width = (E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil() as usize;
+        Self::with_dims(width, depth, seed)
+    }
+
+    /// Creates a sketch of `depth` rows of `width` counters, both at least 1
+    pub fn with_dims(width: usize, depth: usize, seed: u32) -> Self {
+        let (width, depth) = (width.max(1), depth.max(1));
+        CountMinSketch {
+            counters: vec![0; width * depth],
+            width,
+            depth,
+            seed,
+            total: 0,
+            conservative: false,
+        }
+    }
+
+    /// Switches conservative update on or off for the counts added from
+    /// now on
+    pub fn set_conservative(&mut self, conservative: bool) {
+        self.conservative = conservative;
+    }
+
+    /// Counts `item` once, returning its new estimate
+    pub fn add<T: Serialize + ?Sized>(&mut self, item: &T) -> u64 {
+        self.add_n(item, 1)
+    }
+
+    /// Counts `item` `n` times, returning its new estimate
+    pub fn add_n<T: Serialize + ?Sized>(&mut self, item: &T, n: u64) -> u64 {
+        let cells = self.cells(item);
+        self.total = self.total.saturating_add(n);
+        if self.conservative {
+            let estimate = self.min_of(&cells).saturating_add(n);
+            for &c in cells.iter() {
+                self.counters[c] = self.counters[c].max(estimate);
+            }
+            estimate
+        } else {
+            for &c in cells.iter() {
+                self.counters[c] = self.counters[c].saturating_add(n);
+            }
+            self.min_of(&cells)
+        }
+    }
+
+    /// Estimated count of `item`, never below the true count
+    pub fn estimate<T: Serialize + ?Sized>(&self, item: &T) -> u64 {
+        self.min_of(&self.cells(item))
+    }
+
+    /// How far an estimate may overshoot with probability 1 - delta,
+    /// epsilon * total
+    pub fn error_bound(&self) -> f64 {
+        E / self.width as f64 * self.total as f64
+    }
+
+    /// Adds every count of `other`, which must have the same width, depth
+    /// and seed
+    pub fn merge(&mut self, other: &Self) -> Result<(), SketchError> {
+        if (self.width, self.depth, self.seed) != (other.width, other.depth, other.seed) {
+            return Err(SketchError::Incompatible);
+        }
+        for (mine, theirs) in self.counters.iter_mut().zip(&other.counters) {
+            *mine = mine.saturating_add(*theirs);
+        }
+        self.total = self.total.saturating_add(other.total);
+        Ok(())
+    }
+
+    /// Forgets every count, keeping the size
+    pub fn clear(&mut self) {
+        self.counters.fill(0);
+        self.total = 0;
+    }
+
+    /// Sum of all counts added
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Counters per row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    // the counter `item` maps to in every row
+    fn cells<T: Serialize + ?Sized>(&self, item: &T) -> Vec<usize> {
+        let bytes = bincode::serialize(item).expect("Can't convert this item");
+        (0..self.depth)
+            .map(|row| {
+                let seed = self.seed.wrapping_add(row as u32);
+                let hash = hash_mumur3(&bytes, seed).unwrap_or_default();
+                row * self.width + (hash % self.width as u128) as usize
+            })
+            .collect()
+    }
+
+    fn min_of(&self, cells: &[usize]) -> u64 {
+        cells.iter().map(|&c| self.counters[c]).min().unwrap_or(0)
+    }
+}
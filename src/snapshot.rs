@@ -0,0 +1,323 @@
+// Versioned binary snapshots of a HashDict
+//
+// Layout, all integers little endian:
+//
+//   header   magic "MMHD" | version u16 | reserved u16 | seed u32
+//            | bucket count u64 | entry count u64 | header checksum u64
+//   block    entry count u32 | payload length u32 | payload | checksum u128
+//   payload  (key length u32 | key | value length u32 | value)*
+//
+// Keys and values are bincode encoded. Every checksum is murmur3 over the
+// bytes in front of it (for a block that includes its count and length), so
+// flipped bits anywhere in the file are caught on load.
+
+use std::{
+    error::Error,
+    fmt::{self, Debug},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{murmur::hash_mumur3, Bucket, HashDict};
+
+const MAGIC: [u8; 4] = *b"MMHD";
+/// Version written by `save_to`, older or newer files are rejected
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 28;
+const BLOCK_HEADER_LEN: usize = 8;
+const CHECKSUM_SEED: u32 = 0x4d4d4844;
+// blocks are flushed once they pass this size
+const BLOCK_SIZE: usize = 64 * 1024;
+// a single entry may make a block bigger than BLOCK_SIZE, but not this big
+const MAX_BLOCK_LEN: usize = u32::MAX as usize;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The file does not start with the snapshot magic bytes
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// The file ended before the header said it would
+    Truncated,
+    /// The header checksum does not match
+    CorruptHeader,
+    /// A block's checksum does not match, blocks are numbered from 0
+    ChecksumMismatch {
+        block: u64,
+    },
+    /// The checksums passed but the contents make no sense
+    Corrupt(String),
+    Encode(bincode::Error),
+    Decode(bincode::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot i/o error: {e}"),
+            SnapshotError::BadMagic => write!(f, "not a HashDict snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported snapshot version {v} (expected {SNAPSHOT_VERSION})"
+            ),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::CorruptHeader => write!(f, "snapshot header checksum mismatch"),
+            SnapshotError::ChecksumMismatch { block } => {
+                write!(f, "snapshot block {block} checksum mismatch")
+            }
+            SnapshotError::Corrupt(msg) => write!(f, "corrupt snapshot: {msg}"),
+            SnapshotError::Encode(e) => write!(f, "failed to encode entry: {e}"),
+            SnapshotError::Decode(e) => write!(f, "failed to decode entry: {e}"),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            SnapshotError::Encode(e) | SnapshotError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::Io(e)
+        }
+    }
+}
+
+// Writes `path` by way of a temporary file next to it. `write` fills and
+// syncs the temporary file, which is then renamed over `path`, so a crash
+// never leaves a half written file behind. The temporary file is removed
+// again if anything fails, and the directory is synced after the rename so
+// the new name survives a crash too.
+pub(crate) fn write_atomically<E, F>(path: &Path, write: F) -> Result<(), E>
+where
+    E: From<io::Error>,
+    F: FnOnce(&Path) -> Result<(), E>,
+{
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let written = write(&tmp_path).and_then(|()| Ok(fs::rename(&tmp_path, path)?));
+    if written.is_err() {
+        // best effort, the original error is the one worth reporting
+        let _ = fs::remove_file(&tmp_path);
+    }
+    written?;
+    sync_parent_dir(path)?;
+    Ok(())
+}
+
+// makes a rename in the directory holding `path` durable
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+// directories can't be opened and synced like this elsewhere
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn checksum(bytes: &[u8]) -> Result<u128, SnapshotError> {
+    Ok(hash_mumur3(bytes, CHECKSUM_SEED)?)
+}
+
+impl<K, V, B> HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + DeserializeOwned + Debug,
+    V: Serialize + DeserializeOwned + Debug,
+    B: Bucket<K, V>,
+{
+    /// Writes a snapshot to `path`.
+    ///
+    /// The snapshot goes to a temporary file next to `path` first and is
+    /// renamed over it once synced, so a crash never leaves a half written
+    /// snapshot behind. The temporary file is removed again if anything
+    /// fails, and the directory is synced after the rename so the new name
+    /// survives a crash too.
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        write_atomically(path.as_ref(), |tmp_path| {
+            let mut writer = BufWriter::new(File::create(tmp_path)?);
+            self.write_snapshot(&mut writer)?;
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+            Ok(())
+        })
+    }
+
+    /// Loads a snapshot written by `save_to`
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::read_snapshot(reader)
+    }
+
+    /// Streams a snapshot into any writer, one block at a time
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(&MAGIC);
+        header.write_u16::<LittleEndian>(SNAPSHOT_VERSION)?;
+        header.write_u16::<LittleEndian>(0)?;
+        header.write_u32::<LittleEndian>(self.seed)?;
+        header.write_u64::<LittleEndian>(self.arr_length as u64)?;
+        header.write_u64::<LittleEndian>(self.tab_length as u64)?;
+        writer.write_all(&header)?;
+        writer.write_u64::<LittleEndian>(checksum(&header)? as u64)?;
+
+        let mut block = Vec::with_capacity(BLOCK_SIZE + BLOCK_HEADER_LEN);
+        block.resize(BLOCK_HEADER_LEN, 0);
+        let mut count: u32 = 0;
+        self.try_for_each(|k, v| {
+            write_field(&mut block, k)?;
+            write_field(&mut block, v)?;
+            count += 1;
+            if block.len() >= BLOCK_SIZE {
+                flush_block(&mut writer, &mut block, count)?;
+                count = 0;
+            }
+            Ok::<(), SnapshotError>(())
+        })?;
+        if count > 0 {
+            flush_block(&mut writer, &mut block, count)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads a snapshot from any reader, rejecting anything truncated or corrupt
+    pub fn read_snapshot<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let mut header = [0u8; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if header[0..4] != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = LittleEndian::read_u16(&header[4..6]);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        if reader.read_u64::<LittleEndian>()? != checksum(&header)? as u64 {
+            return Err(SnapshotError::CorruptHeader);
+        }
+
+        let seed = LittleEndian::read_u32(&header[8..12]);
+        let arr_length = LittleEndian::read_u64(&header[12..20]);
+        let entries = LittleEndian::read_u64(&header[20..28]);
+        let arr_length = usize::try_from(arr_length)
+            .ok()
+            .filter(|&len| len > 0)
+            .ok_or_else(|| SnapshotError::Corrupt(format!("bad bucket count {arr_length}")))?;
+
+        let mut dict = HashDict::with_bucket_type(arr_length, seed);
+        let mut remaining = entries;
+        let mut block_no: u64 = 0;
+        let mut block = Vec::new();
+        while remaining > 0 {
+            block.resize(BLOCK_HEADER_LEN, 0);
+            reader.read_exact(&mut block)?;
+            let count = LittleEndian::read_u32(&block[0..4]);
+            let len = LittleEndian::read_u32(&block[4..8]) as u64;
+
+            // grows with the data actually read, so a garbage length on a
+            // short file can't trigger a huge allocation
+            let read = (&mut reader).take(len).read_to_end(&mut block)?;
+            if (read as u64) < len {
+                return Err(SnapshotError::Truncated);
+            }
+            if reader.read_u128::<LittleEndian>()? != checksum(&block)? {
+                return Err(SnapshotError::ChecksumMismatch { block: block_no });
+            }
+            if count == 0 || count as u64 > remaining {
+                return Err(SnapshotError::Corrupt(format!(
+                    "block {block_no} claims {count} entries"
+                )));
+            }
+
+            let mut rest = &block[BLOCK_HEADER_LEN..];
+            for _ in 0..count {
+                let key: K = read_field(&mut rest, block_no)?;
+                let value: V = read_field(&mut rest, block_no)?;
+                dict.insert(key, value);
+            }
+            if !rest.is_empty() {
+                return Err(SnapshotError::Corrupt(format!(
+                    "block {block_no} has trailing bytes"
+                )));
+            }
+
+            remaining -= count as u64;
+            block_no += 1;
+        }
+
+        if reader.read(&mut [0u8; 1])? != 0 {
+            return Err(SnapshotError::Corrupt(
+                "trailing data after the last block".to_string(),
+            ));
+        }
+        if dict.len() as u64 != entries {
+            return Err(SnapshotError::Corrupt("duplicate keys".to_string()));
+        }
+        Ok(dict)
+    }
+}
+
+// appends a length prefixed bincode encoding of `item`
+fn write_field<T: Serialize + ?Sized>(block: &mut Vec<u8>, item: &T) -> Result<(), SnapshotError> {
+    let len_at = block.len();
+    block.extend_from_slice(&[0; 4]);
+    bincode::serialize_into(&mut *block, item).map_err(SnapshotError::Encode)?;
+    let len = u32::try_from(block.len() - len_at - 4)
+        .map_err(|_| SnapshotError::Encode(Box::new(bincode::ErrorKind::SizeLimit)))?;
+    LittleEndian::write_u32(&mut block[len_at..len_at + 4], len);
+    Ok(())
+}
+
+fn flush_block<W: Write>(
+    writer: &mut W,
+    block: &mut Vec<u8>,
+    count: u32,
+) -> Result<(), SnapshotError> {
+    if block.len() - BLOCK_HEADER_LEN > MAX_BLOCK_LEN {
+        return Err(SnapshotError::Encode(Box::new(
+            bincode::ErrorKind::SizeLimit,
+        )));
+    }
+    let len = (block.len() - BLOCK_HEADER_LEN) as u32;
+    LittleEndian::write_u32(&mut block[0..4], count);
+    LittleEndian::write_u32(&mut block[4..8], len);
+    writer.write_all(block)?;
+    writer.write_u128::<LittleEndian>(checksum(block)?)?;
+    block.truncate(BLOCK_HEADER_LEN);
+    Ok(())
+}
+
+fn read_field<T: DeserializeOwned>(rest: &mut &[u8], block: u64) -> Result<T, SnapshotError> {
+    let corrupt = || SnapshotError::Corrupt(format!("block {block} has a malformed entry"));
+    if rest.len() < 4 {
+        return Err(corrupt());
+    }
+    let len = LittleEndian::read_u32(&rest[0..4]) as usize;
+    if rest.len() - 4 < len {
+        return Err(corrupt());
+    }
+    let field = &rest[4..4 + len];
+    *rest = &rest[4 + len..];
+    bincode::deserialize(field).map_err(SnapshotError::Decode)
+}
@@ -0,0 +1,199 @@
+// Lazily allocated bucket array behind HashDict
+//
+// The buckets are split into groups of 64. A group keeps a bitmap of which of
+// its buckets hold entries and stores only those, packed in index order, so
+// bucket i of a group sits at popcount(bitmap & ((1 << i) - 1)). Empty buckets
+// take no space: a bucket is created by the first insert that lands in it and
+// dropped again when its last entry goes.
+//
+// Groups are created on first use too, and only the groups holding a bucket
+// are kept, in a BTreeMap keyed by group number, so finding, adding or
+// dropping a group is O(log groups). Creating a HashDict allocates nothing,
+// and a table pays one group header per group in use plus its occupied
+// buckets, however large `arr_length` is and wherever the keys land. Before, every one of the
+// `arr_length` buckets was an allocated-on-creation RbTree.
+
+use std::{
+    collections::{btree_map, BTreeMap},
+    iter, slice,
+};
+
+const GROUP_SIZE: usize = 64;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Group<B> {
+    bitmap: u64,
+    buckets: Vec<B>,
+}
+
+impl<B> Default for Group<B> {
+    fn default() -> Self {
+        Group {
+            bitmap: 0,
+            buckets: Vec::new(),
+        }
+    }
+}
+
+impl<B> Group<B> {
+    // where bucket `slot` is or would go in `buckets`
+    fn rank(&self, slot: usize) -> usize {
+        (self.bitmap & ((1u64 << slot) - 1)).count_ones() as usize
+    }
+
+    fn has(&self, slot: usize) -> bool {
+        self.bitmap & (1u64 << slot) != 0
+    }
+}
+
+pub(crate) type SparseIter<'a, B> = iter::FlatMap<
+    btree_map::Values<'a, usize, Group<B>>,
+    slice::Iter<'a, B>,
+    fn(&'a Group<B>) -> slice::Iter<'a, B>,
+>;
+
+#[derive(Clone, Debug)]
+pub(crate) struct SparseBuckets<B> {
+    len: usize,
+    occupied: usize,
+    // by group number, no empty groups
+    groups: BTreeMap<usize, Group<B>>,
+}
+
+impl<B: Default> SparseBuckets<B> {
+    /// `len` empty buckets, in O(1)
+    pub(crate) fn new(len: usize) -> Self {
+        SparseBuckets {
+            len,
+            occupied: 0,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    /// Number of buckets that hold a bucket value
+    pub(crate) fn occupied(&self) -> usize {
+        self.occupied
+    }
+
+    /// Number of allocated groups
+    pub(crate) fn groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&B> {
+        let (group, slot) = self.locate(index);
+        let group = self.groups.get(&group)?;
+        group.has(slot).then(|| &group.buckets[group.rank(slot)])
+    }
+
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut B> {
+        let (group, slot) = self.locate(index);
+        let group = self.groups.get_mut(&group)?;
+        if !group.has(slot) {
+            return None;
+        }
+        let rank = group.rank(slot);
+        Some(&mut group.buckets[rank])
+    }
+
+    /// The bucket at `index`, created empty if it didn't exist
+    pub(crate) fn get_or_insert(&mut self, index: usize) -> &mut B {
+        let (group, slot) = self.locate(index);
+        let group = self.groups.entry(group).or_default();
+        let rank = group.rank(slot);
+        if !group.has(slot) {
+            group.bitmap |= 1u64 << slot;
+            group.buckets.insert(rank, B::default());
+            self.occupied += 1;
+        }
+        &mut group.buckets[rank]
+    }
+
+    /// Drops the bucket at `index`, back to taking no space
+    pub(crate) fn remove(&mut self, index: usize) -> Option<B> {
+        let (number, slot) = self.locate(index);
+        let group = self.groups.get_mut(&number)?;
+        if !group.has(slot) {
+            return None;
+        }
+        group.bitmap &= !(1u64 << slot);
+        self.occupied -= 1;
+        let bucket = group.buckets.remove(group.rank(slot));
+        if group.bitmap == 0 {
+            self.groups.remove(&number);
+        }
+        Some(bucket)
+    }
+
+    /// Drops every bucket, keeping the bucket count
+    pub(crate) fn clear(&mut self) {
+        self.groups = BTreeMap::new();
+        self.occupied = 0;
+    }
+
+    /// Drops the spare capacity of the groups' buckets
+    pub(crate) fn shrink_to_fit(&mut self, mut shrink_bucket: impl FnMut(&mut B)) {
+        for group in self.groups.values_mut() {
+            group.buckets.shrink_to_fit();
+            group.buckets.iter_mut().for_each(&mut shrink_bucket);
+        }
+    }
+
+    /// The occupied buckets in index order
+    pub(crate) fn iter(&self) -> SparseIter<'_, B> {
+        self.groups
+            .values()
+            .flat_map(group_iter as fn(&Group<B>) -> slice::Iter<'_, B>)
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut B> {
+        self.groups.values_mut().flat_map(|g| g.buckets.iter_mut())
+    }
+
+    /// Drops the buckets `keep` returns false for
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&B) -> bool) {
+        for group in self.groups.values_mut() {
+            // the set bits line up with the buckets, lowest first
+            let mut bits = group.bitmap;
+            let mut kept = 0;
+            group.buckets.retain(|bucket| {
+                let bit = bits & bits.wrapping_neg();
+                bits &= bits - 1;
+                let keep = keep(bucket);
+                if keep {
+                    kept |= bit;
+                }
+                keep
+            });
+            self.occupied -= (group.bitmap & !kept).count_ones() as usize;
+            group.bitmap = kept;
+        }
+        self.groups.retain(|_, group| group.bitmap != 0);
+    }
+
+    fn locate(&self, index: usize) -> (usize, usize) {
+        debug_assert!(index < self.len, "bucket {index} out of {}", self.len);
+        (index / GROUP_SIZE, index % GROUP_SIZE)
+    }
+}
+
+impl<B> IntoIterator for SparseBuckets<B> {
+    type Item = B;
+    type IntoIter =
+        iter::FlatMap<btree_map::IntoValues<usize, Group<B>>, Vec<B>, fn(Group<B>) -> Vec<B>>;
+
+    /// The occupied buckets in index order
+    fn into_iter(self) -> Self::IntoIter {
+        self.groups
+            .into_values()
+            .flat_map(group_into_vec as fn(Group<B>) -> Vec<B>)
+    }
+}
+
+fn group_iter<B>(group: &Group<B>) -> slice::Iter<'_, B> {
+    group.buckets.iter()
+}
+
+fn group_into_vec<B>(group: Group<B>) -> Vec<B> {
+    group.buckets
+}
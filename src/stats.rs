@@ -0,0 +1,130 @@
+// Bucket distribution diagnostics for HashDict
+//
+// Meant for tuning the bucket count and seed: `stats()` sums up how evenly the
+// keys spread over the table and `write_bucket_csv` dumps the raw bucket
+// sizes for plotting.
+
+use std::{
+    fmt::{self, Debug},
+    io::{self, Write},
+};
+
+use serde::Serialize;
+
+use crate::{Bucket, HashDict};
+
+/// Snapshot of how the entries of a `HashDict` are spread over its buckets
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashDictStats {
+    pub entries: usize,
+    pub buckets: usize,
+    /// Entries per bucket
+    pub load_factor: f64,
+    pub empty_buckets: usize,
+    /// Groups of 64 buckets the table has allocated, only the ones holding
+    /// an occupied bucket
+    pub bucket_groups: usize,
+    /// `histogram[n]` is the number of buckets holding exactly `n` entries
+    pub histogram: Vec<usize>,
+    pub max_bucket_len: usize,
+    /// Height of the tallest bucket tree, 0 for an empty table. For list
+    /// buckets this is the longest list, see `Bucket::height`
+    pub max_tree_height: usize,
+    /// Share of entries that would have to share a bucket with another if
+    /// the hash spread them uniformly at random
+    pub expected_collision_rate: f64,
+    /// Share of entries that actually share a bucket with another, counting
+    /// every entry of a bucket but the first
+    pub observed_collision_rate: f64,
+}
+
+impl fmt::Display for HashDictStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "entries:          {}", self.entries)?;
+        writeln!(f, "buckets:          {}", self.buckets)?;
+        writeln!(f, "load factor:      {:.3}", self.load_factor)?;
+        writeln!(f, "empty buckets:    {}", self.empty_buckets)?;
+        writeln!(f, "bucket groups:    {}", self.bucket_groups)?;
+        writeln!(f, "max bucket len:   {}", self.max_bucket_len)?;
+        writeln!(f, "max tree height:  {}", self.max_tree_height)?;
+        writeln!(
+            f,
+            "collision rate:   {:.4} observed, {:.4} expected",
+            self.observed_collision_rate, self.expected_collision_rate
+        )?;
+        write!(f, "bucket sizes:")?;
+        for (size, count) in self.histogram.iter().enumerate() {
+            if *count > 0 {
+                write!(f, " {size}x{count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Collision rate uniform hashing would give, from the expected number of
+// occupied buckets m * (1 - (1 - 1/m)^n)
+fn expected_collision_rate(entries: usize, buckets: usize) -> f64 {
+    if entries == 0 || buckets == 0 {
+        return 0.0;
+    }
+    let (n, m) = (entries as f64, buckets as f64);
+    let occupied = m * (1.0 - (1.0 - 1.0 / m).powf(n));
+    1.0 - occupied / n
+}
+
+impl<K, V, B> HashDict<K, V, B>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+    B: Bucket<K, V>,
+{
+    /// Walks every bucket and reports how evenly the keys are spread
+    pub fn stats(&self) -> HashDictStats {
+        let mut max_tree_height = 0;
+        // unallocated buckets are the empty ones
+        let mut histogram = vec![self.arr_length - self.table.occupied()];
+        for bucket in self.table.iter() {
+            let len = bucket.len();
+            if histogram.len() <= len {
+                histogram.resize(len + 1, 0);
+            }
+            histogram[len] += 1;
+            max_tree_height = max_tree_height.max(bucket.height());
+        }
+
+        let entries = self.tab_length;
+        let buckets = self.arr_length;
+        let occupied = buckets - histogram.first().copied().unwrap_or(0);
+        HashDictStats {
+            entries,
+            buckets,
+            load_factor: if buckets == 0 {
+                0.0
+            } else {
+                entries as f64 / buckets as f64
+            },
+            empty_buckets: buckets - occupied,
+            bucket_groups: self.table.groups(),
+            max_bucket_len: histogram.len().saturating_sub(1),
+            histogram,
+            max_tree_height,
+            expected_collision_rate: expected_collision_rate(entries, buckets),
+            observed_collision_rate: if entries == 0 {
+                0.0
+            } else {
+                (entries - occupied) as f64 / entries as f64
+            },
+        }
+    }
+
+    /// Writes one `bucket,size` line per bucket, after a header line
+    pub fn write_bucket_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "bucket,size")?;
+        for i in 0..self.arr_length {
+            let len = self.table.get(i).map_or(0, |bucket| bucket.len());
+            writeln!(writer, "{i},{len}")?;
+        }
+        writer.flush()
+    }
+}
@@ -0,0 +1,147 @@
+// Approximate top k on a Count-Min sketch
+//
+// Every item goes into the sketch, and the k items with the highest
+// estimates so far are kept by name. They sit in an RbTree ordered by
+// (estimate, key), so the weakest one is found in O(log k) when a new
+// estimate beats it. Memory is the sketch plus k keys, however long the
+// stream runs.
+//
+// An item's estimate only counts from the sketch, so one that was dropped
+// and comes back picks up where its estimate left off instead of at 1.
+
+use std::{
+    cmp::Reverse,
+    fmt::{self, Debug},
+    mem,
+};
+
+use serde::Serialize;
+
+use crate::{CountMinSketch, HashDict, RbTree};
+
+/// # Top K
+///
+/// The k most frequent items of a stream, approximately.
+///
+/// public functions:
+/// - new
+/// - with_sketch
+/// - add
+/// - add_n
+/// - top
+/// - estimate
+/// - len
+/// - k
+/// - sketch
+pub struct TopK<K>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+{
+    sketch: CountMinSketch,
+    k: usize,
+    counts: HashDict<K, u64>,
+    ranked: RbTree<(u64, K), ()>,
+}
+
+impl<K> TopK<K>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+{
+    /// Tracks the `k` most frequent items, counting with a conservative
+    /// sketch that overshoots by 0.1% of the total at most 99.9% of the
+    /// time
+    pub fn new(k: usize, seed: u32) -> Self {
+        let mut sketch = CountMinSketch::new(0.001, 0.001, seed);
+        sketch.set_conservative(true);
+        Self::with_sketch(k, sketch)
+    }
+
+    /// Tracks the `k` most frequent items, counting with `sketch`
+    pub fn with_sketch(k: usize, sketch: CountMinSketch) -> Self {
+        let seed = sketch.seed();
+        TopK {
+            sketch,
+            k,
+            counts: HashDict::new(k.max(1), seed),
+            ranked: RbTree::new(),
+        }
+    }
+
+    /// Counts `key` once
+    pub fn add(&mut self, key: K) -> &mut Self {
+        self.add_n(key, 1)
+    }
+
+    /// Counts `key` `n` times
+    pub fn add_n(&mut self, key: K, n: u64) -> &mut Self {
+        let estimate = self.sketch.add_n(&key, n);
+        if let Some(count) = self.counts.get_mut(&key) {
+            let old = mem::replace(count, estimate);
+            let (_, key) = self
+                .ranked
+                .remove_entry(&(old, key))
+                .expect("tracked key is ranked")
+                .0;
+            self.ranked.insert((estimate, key), ());
+            return self;
+        }
+
+        if self.counts.len() >= self.k {
+            match self.ranked.first() {
+                Some(((weakest, _), _)) if *weakest < estimate => {
+                    if let Some(((_, dropped), _)) = self.ranked.pop_first() {
+                        self.counts.delete(&dropped);
+                    }
+                }
+                _ => return self,
+            }
+        }
+        self.counts.insert(key.clone(), estimate);
+        self.ranked.insert((estimate, key), ());
+        self
+    }
+
+    /// The tracked items with their estimates, highest first. Equal
+    /// estimates come in key order.
+    pub fn top(&self) -> Vec<(&K, u64)> {
+        let mut top: Vec<(&K, u64)> = self
+            .ranked
+            .iter_ref()
+            .map(|((count, key), _)| (key, *count))
+            .collect();
+        // stable, so equal counts stay in key order
+        top.sort_by_key(|&(_, count)| Reverse(count));
+        top
+    }
+
+    /// Estimated count of `key`, tracked or not
+    pub fn estimate<Q: Serialize + ?Sized>(&self, key: &Q) -> u64 {
+        self.sketch.estimate(key)
+    }
+
+    /// Number of tracked items, at most k
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn sketch(&self) -> &CountMinSketch {
+        &self.sketch
+    }
+}
+
+impl<K> Debug for TopK<K>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.top()).finish()
+    }
+}
@@ -18,12 +18,18 @@ impl<T: PartialOrd> Node<T> {
     ) -> Box<Node<T>> {
         Box::new(Node {
             val: val_in,
-            left_child: left_child,
-            right_child: right_child,
+            left_child,
+            right_child,
         })
     }
 }
 
+impl<T: PartialOrd + Clone> Default for Tree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: PartialOrd + Clone> Tree<T> {
     // creating a new Tree
     pub fn new() -> Self {
@@ -56,7 +62,7 @@ impl<T: PartialOrd + Clone> Tree<T> {
             }
         }
 
-        return self;
+        self
     }
 
     // public search function
@@ -65,7 +71,7 @@ impl<T: PartialOrd + Clone> Tree<T> {
     }
 
     fn search(&self, val: T) -> Option<&Node<T>> {
-        return self.search_recurse(&self.root, val);
+        self.search_recurse(&self.root, val)
     }
 
     fn search_recurse<'a>(&self, node: &'a Option<Box<Node<T>>>, val: T) -> Option<&'a Node<T>> {
@@ -83,7 +89,7 @@ impl<T: PartialOrd + Clone> Tree<T> {
         while let Some(n) = current_node.left_child {
             current_node = n;
         }
-        return current_node;
+        current_node
     }
 
     // delete
@@ -131,7 +137,7 @@ impl<T: PartialOrd + Clone> Tree<T> {
                 return Some(&node.val);
             }
         }
-        return None;
+        None
     }
 
     pub fn max(&self) -> Option<&T> {
@@ -143,7 +149,7 @@ impl<T: PartialOrd + Clone> Tree<T> {
                 return Some(&node.val);
             }
         }
-        return None;
+        None
     }
 
     pub fn height(&self) -> i32 {
@@ -159,7 +165,40 @@ impl<T: PartialOrd + Clone> Tree<T> {
                 }
             }
         }
-        return height_recurse(current_node);
+        height_recurse(current_node)
+    }
+}
+
+#[cfg(feature = "serde-impls")]
+impl<T: PartialOrd> Tree<T> {
+    // in-order walk that lends each value to `f`, stopping at the first error
+    pub(crate) fn try_for_each<E, F>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&T) -> Result<(), E>,
+    {
+        fn walk<T: PartialOrd, E>(
+            node: &Option<Box<Node<T>>>,
+            f: &mut dyn FnMut(&T) -> Result<(), E>,
+        ) -> Result<(), E> {
+            if let Some(ref n) = node {
+                walk(&n.left_child, f)?;
+                f(&n.val)?;
+                walk(&n.right_child, f)?;
+            }
+            Ok(())
+        }
+
+        walk(&self.root, &mut f)
+    }
+
+    // number of values in the tree
+    pub(crate) fn count(&self) -> usize {
+        let mut count = 0;
+        let _ = self.try_for_each(|_| -> Result<(), ()> {
+            count += 1;
+            Ok(())
+        });
+        count
     }
 }
 
@@ -183,6 +222,6 @@ impl<T: PartialOrd + std::fmt::Debug> Display for Tree<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut out_str = String::new();
         self.build_inorder_str(&self.root, &mut out_str);
-        return write!(f, "{}", out_str);
+        write!(f, "{}", out_str)
     }
 }
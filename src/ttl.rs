@@ -0,0 +1,259 @@
+// HashDict whose entries can expire
+//
+// Every entry remembers its deadline next to its value, and lookups treat an
+// entry past its deadline as missing. The deadlines are also kept in an
+// RbTree ordered by (deadline, key), smallest first, so `purge_expired` only
+// walks the entries that have actually expired. Overwriting or removing an
+// entry takes its old deadline out of the tree, so the tree holds exactly
+// one deadline per expiring entry.
+//
+// Time comes from a `Clock`, `SystemClock` in production and a
+// `ManualClock` in tests that only moves when it is told to.
+
+use std::{
+    borrow::Borrow,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{HashDict, RbTree};
+
+/// Source of the current time for a `TtlDict`
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that stands still until `advance` is called. Clones share the
+/// same time, so a test can keep one and hand the other to the dict.
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().expect("clock lock poisoned") += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().expect("clock lock poisoned")
+    }
+}
+
+/// # TTL Dictionary
+///
+/// A `HashDict` whose entries may be given a time to live.
+///
+/// public functions:
+/// - new
+/// - with_clock
+/// - insert
+/// - insert_with_ttl
+/// - get
+/// - get_ref
+/// - get_mut
+/// - contains_key
+/// - ttl
+/// - pop
+/// - purge_expired
+/// - len
+pub struct TtlDict<K, V, C = SystemClock>
+where
+    K: PartialOrd + Serialize + Debug,
+    V: Debug,
+{
+    dict: HashDict<K, (V, Option<Instant>)>,
+    deadlines: RbTree<(Instant, K), ()>,
+    clock: C,
+}
+
+impl<K, V> TtlDict<K, V>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Debug,
+{
+    /// Creates a table of `len` buckets running on the system clock
+    pub fn new(len: usize, seed: u32) -> Self {
+        Self::with_clock(len, seed, SystemClock)
+    }
+}
+
+impl<K, V, C> TtlDict<K, V, C>
+where
+    K: PartialOrd + Serialize + Debug + Clone,
+    V: Debug,
+    C: Clock,
+{
+    pub fn with_clock(len: usize, seed: u32, clock: C) -> Self {
+        TtlDict {
+            dict: HashDict::new(len, seed),
+            deadlines: RbTree::new(),
+            clock,
+        }
+    }
+
+    /// Inserts an entry that never expires, replacing any entry under the
+    /// same key
+    pub fn insert(&mut self, key: K, value: V) -> &mut Self {
+        let key = self.forget_deadline(key);
+        self.dict.insert(key, (value, None));
+        self
+    }
+
+    /// Inserts an entry that disappears `ttl` from now, replacing any entry
+    /// under the same key. A `ttl` too long for the clock to represent
+    /// never expires.
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> &mut Self {
+        let deadline = self.clock.now().checked_add(ttl);
+        let key = self.forget_deadline(key);
+        if let Some(deadline) = deadline {
+            self.deadlines.insert((deadline, key.clone()), ());
+        }
+        self.dict.insert(key, (value, deadline));
+        self
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        V: Clone,
+    {
+        self.get_ref(key).cloned()
+    }
+
+    pub fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let now = self.clock.now();
+        match self.dict.get_ref(key)? {
+            (value, deadline) if is_live(*deadline, now) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let now = self.clock.now();
+        match self.dict.get_mut(key)? {
+            (value, deadline) if is_live(*deadline, now) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.get_ref(key).is_some()
+    }
+
+    /// Time left before `key` expires, `None` if it is missing, expired
+    /// or never expires
+    pub fn ttl<Q>(&self, key: &Q) -> Option<Duration>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let now = self.clock.now();
+        let deadline = self.dict.get_ref(key)?.1?;
+        deadline
+            .checked_duration_since(now)
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Removes an entry, handing back its value if it hadn't expired
+    pub fn pop<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        let now = self.clock.now();
+        let (key, (value, deadline)) = self.dict.remove_entry(key)?;
+        if let Some(deadline) = deadline {
+            self.deadlines.remove_entry(&(deadline, key));
+        }
+        is_live(deadline, now).then_some(value)
+    }
+
+    /// Removes every expired entry and returns how many there were
+    pub fn purge_expired(&mut self) -> usize {
+        let now = self.clock.now();
+        let mut purged = 0;
+        while self.deadlines.first().is_some_and(|((d, _), _)| *d <= now) {
+            let Some(((_, key), _)) = self.deadlines.pop_first() else {
+                break;
+            };
+            self.dict.delete(&key);
+            purged += 1;
+        }
+        purged
+    }
+
+    /// Number of stored entries, counting expired ones that haven't been
+    /// purged yet
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    // takes the deadline of the entry under `key`, if any, out of the index
+    // and hands the key back
+    fn forget_deadline(&mut self, key: K) -> K {
+        match self.dict.get_ref(&key).and_then(|e| e.1) {
+            Some(deadline) => {
+                let ((_, key), _) = self
+                    .deadlines
+                    .remove_entry(&(deadline, key))
+                    .expect("deadline is indexed");
+                key
+            }
+            None => key,
+        }
+    }
+}
+
+// entries without a deadline never expire
+fn is_live(deadline: Option<Instant>, now: Instant) -> bool {
+    deadline.is_none_or(|d| now < d)
+}
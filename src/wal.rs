@@ -0,0 +1,496 @@
+// Crash-safe HashDict persistence through an append-only write-ahead log
+//
+// A DurableHashDict lives in a directory holding two files:
+//
+//   snapshot  the table as of the last compaction, see snapshot.rs
+//   wal       every insert and delete since then
+//
+// Layout of the log, all integers little endian:
+//
+//   header   magic "MMWL" | version u16 | reserved u16
+//   record   payload length u32 | checksum u64 | payload
+//   payload  tag u8 | key | value (inserts only)
+//
+// Keys and values are bincode encoded and the checksum is murmur3 over the
+// length and payload. A change is appended to the log before it is applied
+// in memory, and opening the directory loads the snapshot and replays the log
+// on top of it.
+
+use std::{
+    borrow::Borrow,
+    error::Error,
+    fmt::{self, Debug},
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{murmur::Murmur3, HashDict, SnapshotError};
+
+const MAGIC: [u8; 4] = *b"MMWL";
+/// Version of the log format, older or newer logs are rejected
+pub const WAL_VERSION: u16 = 1;
+
+const HEADER_LEN: u64 = 8;
+const RECORD_HEADER_LEN: u64 = 12;
+const CHECKSUM_SEED: u32 = 0x4d4d574c;
+const SNAPSHOT_FILE: &str = "snapshot";
+const WAL_FILE: &str = "wal";
+// log records written before the log is folded into a new snapshot
+const DEFAULT_COMPACT_AFTER: u64 = 100_000;
+
+// how far past a length that runs off the end replay looks for a whole
+// record before calling it a torn tail
+const RESYNC_WINDOW: usize = 1 << 20;
+
+const TAG_INSERT: u8 = 1;
+const TAG_DELETE: u8 = 2;
+
+/// How often the log is synced to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Sync after every write, nothing acknowledged is ever lost
+    Always,
+    /// Sync once every n writes, a crash loses at most the last n
+    Batched(u32),
+    /// Leave it to the OS, writes reach the file when the buffer fills, on
+    /// `sync` and on drop
+    Never,
+}
+
+#[derive(Debug)]
+pub enum WalError {
+    Io(io::Error),
+    Snapshot(SnapshotError),
+    /// The log does not start with the log magic bytes
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// A record before the end of the log is damaged, `offset` is where it starts
+    Corrupt {
+        offset: u64,
+    },
+    Encode(bincode::Error),
+    Decode(bincode::Error),
+}
+
+impl fmt::Display for WalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalError::Io(e) => write!(f, "log i/o error: {e}"),
+            WalError::Snapshot(e) => write!(f, "{e}"),
+            WalError::BadMagic => write!(f, "not a HashDict log"),
+            WalError::UnsupportedVersion(v) => {
+                write!(f, "unsupported log version {v} (expected {WAL_VERSION})")
+            }
+            WalError::Corrupt { offset } => write!(f, "corrupt log record at offset {offset}"),
+            WalError::Encode(e) => write!(f, "failed to encode log record: {e}"),
+            WalError::Decode(e) => write!(f, "failed to decode log record: {e}"),
+        }
+    }
+}
+
+impl Error for WalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WalError::Io(e) => Some(e),
+            WalError::Snapshot(e) => Some(e),
+            WalError::Encode(e) | WalError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WalError {
+    fn from(e: io::Error) -> Self {
+        WalError::Io(e)
+    }
+}
+
+impl From<SnapshotError> for WalError {
+    fn from(e: SnapshotError) -> Self {
+        WalError::Snapshot(e)
+    }
+}
+
+// murmur3 over the length and the payload, as if they were back to back
+fn checksum(len: u32, payload: &[u8]) -> u64 {
+    let mut hasher = Murmur3::new(CHECKSUM_SEED);
+    hasher.write(&len.to_le_bytes());
+    hasher.write(payload);
+    hasher.finish() as u64
+}
+
+/// # Durable Hash Dictionary
+///
+/// A `HashDict` whose changes are logged to disk before they are applied.
+///
+/// public functions:
+/// - open
+/// - insert
+/// - delete
+/// - pop
+/// - get
+/// - get_ref
+/// - contains_key
+/// - compact
+/// - take_compact_error
+/// - sync
+/// - len
+pub struct DurableHashDict<K, V>
+where
+    K: PartialOrd + Serialize,
+    V: Debug,
+{
+    dict: HashDict<K, V>,
+    dir: PathBuf,
+    log: BufWriter<File>,
+    fsync: FsyncPolicy,
+    compact_after: Option<u64>,
+    // records in the log, and records written since the last sync
+    log_records: u64,
+    unsynced: u32,
+    // a failed automatic compaction is kept here and retried later
+    compact_error: Option<WalError>,
+    retry_compact_at: u64,
+}
+
+impl<K, V> DurableHashDict<K, V>
+where
+    K: PartialOrd + Serialize + DeserializeOwned + Debug,
+    V: Serialize + DeserializeOwned + Debug,
+{
+    /// Opens the table stored in `dir`, creating it if needed.
+    ///
+    /// `len` and `seed` are only used when `dir` holds no snapshot yet. A
+    /// record cut short at the end of the log, as left by a crash mid-write,
+    /// is dropped and the log truncated to the last whole record. Damage
+    /// anywhere else is reported as `WalError::Corrupt`.
+    pub fn open<P: AsRef<Path>>(
+        dir: P,
+        len: usize,
+        seed: u32,
+        fsync: FsyncPolicy,
+    ) -> Result<Self, WalError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        let mut dict = if snapshot_path.exists() {
+            HashDict::load_from(&snapshot_path)?
+        } else {
+            HashDict::new(len, seed)
+        };
+
+        let wal_path = dir.join(WAL_FILE);
+        let log_records = if wal_path.exists() {
+            replay(&wal_path, &mut dict)?
+        } else {
+            create_log(&wal_path)?;
+            0
+        };
+        let file = OpenOptions::new().append(true).open(&wal_path)?;
+
+        Ok(DurableHashDict {
+            dict,
+            dir,
+            log: BufWriter::new(file),
+            fsync,
+            compact_after: Some(DEFAULT_COMPACT_AFTER),
+            log_records,
+            unsynced: 0,
+            compact_error: None,
+            retry_compact_at: 0,
+        })
+    }
+
+    /// Sets how many log records trigger a compaction, `None` turns
+    /// automatic compaction off
+    pub fn set_compact_after(&mut self, records: Option<u64>) {
+        self.compact_after = records;
+    }
+
+    /// Logs and applies an insert, replacing the value if the key is
+    /// already in the table
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), WalError> {
+        let mut payload = vec![TAG_INSERT];
+        bincode::serialize_into(&mut payload, &key).map_err(WalError::Encode)?;
+        bincode::serialize_into(&mut payload, &value).map_err(WalError::Encode)?;
+        self.append(&payload)?;
+        self.dict.insert(key, value);
+        self.maybe_compact();
+        Ok(())
+    }
+
+    /// Logs and applies a delete, nothing is logged if the key is missing
+    pub fn delete<Q>(&mut self, key: &Q) -> Result<(), WalError>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.pop(key).map(|_| ())
+    }
+
+    pub fn pop<Q>(&mut self, key: &Q) -> Result<Option<V>, WalError>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        if !self.dict.contains_key(key) {
+            return Ok(None);
+        }
+        // borrowed keys encode like the owned key, see HashDict::bucket_index
+        let mut payload = vec![TAG_DELETE];
+        bincode::serialize_into(&mut payload, key).map_err(WalError::Encode)?;
+        self.append(&payload)?;
+        let value = self.dict.pop(key);
+        self.maybe_compact();
+        Ok(value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+        V: Clone,
+    {
+        self.dict.get(key)
+    }
+
+    pub fn get_ref<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.get_ref(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialOrd + Serialize + ?Sized,
+    {
+        self.dict.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+
+    /// The in-memory table, for anything the wrapper does not forward
+    pub fn dict(&self) -> &HashDict<K, V> {
+        &self.dict
+    }
+
+    /// Writes out and syncs anything the fsync policy has held back
+    pub fn sync(&mut self) -> Result<(), WalError> {
+        self.log.flush()?;
+        self.log.get_ref().sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Folds the log into a fresh snapshot and starts an empty log.
+    ///
+    /// The snapshot is renamed into place before the log is reset. A crash in
+    /// between replays the old log over the new snapshot on the next open,
+    /// which is harmless since replaying the same inserts and deletes in
+    /// order ends in the same table.
+    pub fn compact(&mut self) -> Result<(), WalError> {
+        self.log.flush()?;
+        self.dict.save_to(self.dir.join(SNAPSHOT_FILE))?;
+
+        let wal_path = self.dir.join(WAL_FILE);
+        create_log(&wal_path)?;
+        let file = OpenOptions::new().append(true).open(&wal_path)?;
+        self.log = BufWriter::new(file);
+        self.log_records = 0;
+        self.unsynced = 0;
+        self.retry_compact_at = 0;
+        Ok(())
+    }
+
+    /// Hands out the error of the last automatic compaction that failed,
+    /// if it wasn't taken yet. The writes themselves succeeded, only the
+    /// log keeps growing until a compaction goes through.
+    pub fn take_compact_error(&mut self) -> Option<WalError> {
+        self.compact_error.take()
+    }
+
+    fn append(&mut self, payload: &[u8]) -> Result<(), WalError> {
+        let len = u32::try_from(payload.len())
+            .map_err(|_| WalError::Encode(Box::new(bincode::ErrorKind::SizeLimit)))?;
+        self.log.write_u32::<LittleEndian>(len)?;
+        self.log.write_u64::<LittleEndian>(checksum(len, payload))?;
+        self.log.write_all(payload)?;
+        self.log_records += 1;
+        self.unsynced += 1;
+
+        match self.fsync {
+            FsyncPolicy::Always => self.sync(),
+            FsyncPolicy::Batched(n) if self.unsynced >= n => self.sync(),
+            FsyncPolicy::Batched(_) | FsyncPolicy::Never => Ok(()),
+        }
+    }
+
+    // The write that triggered the compaction is already logged and
+    // applied, so a failure here doesn't fail it. The error is kept for
+    // `take_compact_error` and the compaction retried after another
+    // `compact_after` records.
+    fn maybe_compact(&mut self) {
+        let Some(n) = self.compact_after else {
+            return;
+        };
+        if self.log_records < n.max(self.retry_compact_at) {
+            return;
+        }
+        if let Err(e) = self.compact() {
+            self.retry_compact_at = self.log_records.saturating_add(n);
+            self.compact_error = Some(e);
+        }
+    }
+}
+
+impl<K, V> Drop for DurableHashDict<K, V>
+where
+    K: PartialOrd + Serialize,
+    V: Debug,
+{
+    fn drop(&mut self) {
+        // errors can't be reported from here, call `sync` to see them
+        let _ = self.log.flush();
+    }
+}
+
+// writes an empty log over `path`
+fn create_log(path: &Path) -> Result<(), WalError> {
+    let mut file = File::create(path)?;
+    file.write_all(&MAGIC)?;
+    file.write_u16::<LittleEndian>(WAL_VERSION)?;
+    file.write_u16::<LittleEndian>(0)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// Applies every record in the log to `dict` and returns how many there were,
+// truncating a torn record off the end
+fn replay<K, V>(path: &Path, dict: &mut HashDict<K, V>) -> Result<u64, WalError>
+where
+    K: PartialOrd + Serialize + DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    let file_len = fs::metadata(path)?.len();
+    if file_len < HEADER_LEN {
+        // the crash hit while the log was being created
+        create_log(path)?;
+        return Ok(0);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; HEADER_LEN as usize];
+    reader.read_exact(&mut header)?;
+    if header[0..4] != MAGIC {
+        return Err(WalError::BadMagic);
+    }
+    let version = LittleEndian::read_u16(&header[4..6]);
+    if version != WAL_VERSION {
+        return Err(WalError::UnsupportedVersion(version));
+    }
+
+    let mut offset = HEADER_LEN;
+    let mut records = 0;
+    let mut payload = Vec::new();
+    while offset < file_len {
+        if file_len - offset < RECORD_HEADER_LEN {
+            break;
+        }
+        let len = reader.read_u32::<LittleEndian>()?;
+        let sum = reader.read_u64::<LittleEndian>()?;
+        let end = offset + RECORD_HEADER_LEN + len as u64;
+        if end > file_len {
+            // a crash only ever cuts the last record short, so a length
+            // running past the end is a torn tail only if no whole record
+            // follows it, anything else is a damaged length
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest)?;
+            if holds_record(&rest) {
+                return Err(WalError::Corrupt { offset });
+            }
+            break;
+        }
+
+        payload.clear();
+        (&mut reader).take(len as u64).read_to_end(&mut payload)?;
+        if checksum(len, &payload) != sum {
+            // only the last record can have been torn by a crash
+            if end == file_len {
+                break;
+            }
+            return Err(WalError::Corrupt { offset });
+        }
+        apply(dict, &payload, offset)?;
+
+        offset = end;
+        records += 1;
+    }
+
+    if offset < file_len {
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(offset)?;
+        file.sync_all()?;
+    }
+    Ok(records)
+}
+
+// Whether a record with a matching checksum starts in the first
+// RESYNC_WINDOW bytes of `bytes`. Only candidates whose payload starts with
+// a record tag are hashed, and the window keeps a long damaged tail from
+// being rehashed from every offset.
+fn holds_record(bytes: &[u8]) -> bool {
+    let header_len = RECORD_HEADER_LEN as usize;
+    let starts = bytes.len().saturating_sub(header_len).min(RESYNC_WINDOW);
+    (0..starts).any(|start| {
+        let len = LittleEndian::read_u32(&bytes[start..start + 4]);
+        let payload_at = start + header_len;
+        let Some(payload) = bytes.get(payload_at..payload_at + len as usize) else {
+            return false;
+        };
+        matches!(payload.first(), Some(&(TAG_INSERT | TAG_DELETE)))
+            && checksum(len, payload) == LittleEndian::read_u64(&bytes[start + 4..payload_at])
+    })
+}
+
+// decodes a single record and applies it to `dict`
+fn apply<K, V>(dict: &mut HashDict<K, V>, payload: &[u8], offset: u64) -> Result<(), WalError>
+where
+    K: PartialOrd + Serialize + DeserializeOwned + Debug,
+    V: DeserializeOwned + Debug,
+{
+    let corrupt = || WalError::Corrupt { offset };
+    let (&tag, mut rest) = payload.split_first().ok_or_else(corrupt)?;
+    let key: K = bincode::deserialize_from(&mut rest).map_err(WalError::Decode)?;
+    let value: Option<V> = match tag {
+        TAG_INSERT => Some(bincode::deserialize_from(&mut rest).map_err(WalError::Decode)?),
+        TAG_DELETE => None,
+        _ => return Err(corrupt()),
+    };
+    if !rest.is_empty() {
+        return Err(corrupt());
+    }
+
+    match value {
+        Some(value) => {
+            dict.insert(key, value);
+        }
+        None => {
+            dict.remove_entry(&key);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use murmur_hash_rust::BloomFilter;
+// Filter sized for `n` items holding the keys 0 to n
+fn filled(n: u64, fp_rate: f64) -> BloomFilter {
+    let mut filter = BloomFilter::new(n as usize, fp_rate, 42);
+    for k in 0..n {
+        filter.insert(&k);
+    }
+    filter
+}
+
+#[cfg(test)]
+mod bloom_test {
+    use murmur_hash_rust::{BloomError, BloomFilter};
+
+    use crate::filled;
+
+    #[test]
+    fn test_no_false_negatives_and_fp_rate() {
+        let filter = filled(10000, 0.01);
+        // 95851 bits and 7 hashes for 1%
+        assert_eq!(filter.bit_count(), 95851);
+        assert_eq!(filter.hash_count(), 7);
+        assert!((0..10000u64).all(|k| filter.contains(&k)));
+
+        let false_positives = (10000..110000u64).filter(|k| filter.contains(k)).count();
+        assert!(false_positives < 1300, "{false_positives} false positives");
+        assert!((filter.false_positive_rate() - 0.01).abs() < 0.003);
+    }
+
+    #[test]
+    fn test_insert_and_estimated_count() {
+        let mut filter = BloomFilter::new(1000, 0.01, 42);
+        assert!(filter.is_empty());
+        assert!(filter.insert("key"));
+        assert!(!filter.insert("key"));
+        assert!(filter.contains("key"));
+        assert!(!filter.contains("other"));
+
+        let filter = filled(5000, 0.01);
+        let estimate = filter.estimated_count();
+        assert!((estimate - 5000.0).abs() < 100.0, "estimated {estimate}");
+        let mut cleared = filter.clone();
+        cleared.clear();
+        assert!(cleared.is_empty());
+        assert_eq!(cleared.estimated_count(), 0.0);
+    }
+
+    #[test]
+    fn test_union_and_intersection() {
+        let mut evens = BloomFilter::new(1000, 0.01, 42);
+        let mut small = BloomFilter::new(1000, 0.01, 42);
+        for k in 0..500u64 {
+            evens.insert(&(k * 2));
+            small.insert(&k);
+        }
+
+        let mut union = evens.clone();
+        union.union_with(&small).unwrap();
+        assert!((0..500u64).all(|k| union.contains(&k) && union.contains(&(k * 2))));
+
+        let mut both = evens.clone();
+        both.intersect_with(&small).unwrap();
+        assert!((0..250u64).all(|k| both.contains(&(k * 2))));
+        assert!((500..1000u64).filter(|k| both.contains(&(k * 2))).count() < 25);
+
+        let other_seed = BloomFilter::new(1000, 0.01, 7);
+        assert!(matches!(
+            union.union_with(&other_seed),
+            Err(BloomError::Incompatible)
+        ));
+        assert!(matches!(
+            union.intersect_with(&BloomFilter::new(10, 0.01, 42)),
+            Err(BloomError::Incompatible)
+        ));
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let filter = filled(1000, 0.05);
+        let bytes = filter.to_bytes();
+        let loaded = BloomFilter::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, filter);
+        assert!((0..1000u64).all(|k| loaded.contains(&k)));
+
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 1;
+        assert!(matches!(
+            BloomFilter::from_bytes(&flipped),
+            Err(BloomError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            BloomFilter::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BloomError::Truncated)
+        ));
+        assert!(matches!(
+            BloomFilter::from_bytes(b"not a filter at all, no sir"),
+            Err(BloomError::BadMagic)
+        ));
+        let mut newer = bytes.clone();
+        newer[4] = 9;
+        assert!(matches!(
+            BloomFilter::from_bytes(&newer),
+            Err(BloomError::UnsupportedVersion(9))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt::Debug;
+
+use murmur_hash_rust::{Bucket, HashDict};
+// Same workload for every kind of bucket, checked against the expected contents
+fn exercise<B: Bucket<u32, u32> + Debug>(buckets: usize) -> HashDict<u32, u32, B> {
+    let mut d: HashDict<u32, u32, B> = HashDict::with_bucket_type(buckets, 42);
+    for i in 0..2000 {
+        d.insert(i, i);
+    }
+    for i in (0..2000).step_by(3) {
+        d.insert(i, i * 10);
+    }
+    for i in (0..2000).step_by(2) {
+        assert_eq!(d.remove_entry(&i).map(|(k, _)| k), Some(i));
+    }
+    assert_eq!(d.len(), 1000);
+    for i in 0..2000 {
+        let expected = match (i % 2, i % 3) {
+            (0, _) => None,
+            (_, 0) => Some(i * 10),
+            _ => Some(i),
+        };
+        assert_eq!(d.get(&i), expected, "key {i}");
+    }
+    d
+}
+
+#[cfg(test)]
+mod bucket_test {
+    use murmur_hash_rust::{
+        Bucket, HashDict, HybridBucket, RbTree, SmallBucket, TREEIFY_THRESHOLD, UNTREEIFY_THRESHOLD,
+    };
+
+    use crate::exercise;
+
+    #[test]
+    fn test_rb_tree_buckets() {
+        let d = exercise::<RbTree<u32, u32>>(16);
+        let default: HashDict<u32, u32> = d.iter().collect();
+        assert_eq!(default, d);
+    }
+
+    #[test]
+    fn test_small_buckets() {
+        // many entries per bucket on purpose, so lists grow and shrink
+        exercise::<SmallBucket<u32, u32>>(16);
+        exercise::<SmallBucket<u32, u32>>(4096);
+    }
+
+    #[test]
+    fn test_hybrid_buckets() {
+        exercise::<HybridBucket<u32, u32>>(16);
+        exercise::<HybridBucket<u32, u32>>(4096);
+    }
+
+    #[test]
+    fn test_extract_if() {
+        // the keys left by `exercise` are odd, 200 of them multiples of 5
+        let mut trees = exercise::<RbTree<u32, u32>>(16);
+        let mut lists = exercise::<SmallBucket<u32, u32>>(16);
+        let mut hybrids = exercise::<HybridBucket<u32, u32>>(64);
+        assert_eq!(trees.extract_if(|k, _| k % 5 == 0).len(), 200);
+        assert_eq!(lists.extract_if(|k, _| k % 5 == 0).len(), 200);
+        assert_eq!(hybrids.extract_if(|k, _| k % 5 == 0).len(), 200);
+        for len in [trees.len(), lists.len(), hybrids.len()] {
+            assert_eq!(len, 800);
+        }
+        assert_eq!(
+            trees.iter().collect::<Vec<_>>(),
+            lists.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            trees.iter().collect::<Vec<_>>(),
+            hybrids.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_small_bucket_variants() {
+        let mut b: SmallBucket<&str, i32> = SmallBucket::default();
+        assert!(matches!(b, SmallBucket::Empty));
+        b.insert("a", 1);
+        assert!(matches!(b, SmallBucket::One(_)));
+        assert_eq!(b.insert("a", 2), Some(1));
+        b.insert("b", 3);
+        assert!(matches!(b, SmallBucket::Many(_)));
+        assert_eq!(b.remove_entry(&"a"), Some(("a", 2)));
+        assert!(matches!(b, SmallBucket::One(("b", 3))));
+        assert_eq!(b.remove_entry(&"b"), Some(("b", 3)));
+        assert!(b.is_empty());
+    }
+
+    #[test]
+    fn test_hybrid_treeify() {
+        let mut b: HybridBucket<usize, usize> = HybridBucket::default();
+        for i in 0..TREEIFY_THRESHOLD {
+            b.insert(i, i);
+        }
+        assert!(!b.is_tree());
+        b.insert(TREEIFY_THRESHOLD, 0);
+        assert!(b.is_tree());
+        assert_eq!(b.len(), TREEIFY_THRESHOLD + 1);
+
+        // stays a tree until it drops to the lower threshold
+        let mut next = 0;
+        while b.len() > UNTREEIFY_THRESHOLD + 1 {
+            b.remove_entry(&next);
+            next += 1;
+        }
+        assert!(b.is_tree());
+        b.remove_entry(&next);
+        assert!(!b.is_tree());
+        assert_eq!(b.len(), UNTREEIFY_THRESHOLD);
+
+        let mut keys: Vec<usize> = b.iter().map(|(k, _)| *k).collect();
+        keys.sort();
+        assert_eq!(keys, (next + 1..=TREEIFY_THRESHOLD).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stats_and_snapshot() {
+        let d = exercise::<SmallBucket<u32, u32>>(64);
+        let s = d.stats();
+        assert_eq!(s.entries, 1000);
+        assert_eq!(s.max_tree_height, s.max_bucket_len);
+
+        let mut bytes = Vec::new();
+        d.write_snapshot(&mut bytes).unwrap();
+        let back: HashDict<u32, u32, HybridBucket<u32, u32>> =
+            HashDict::read_snapshot(&bytes[..]).unwrap();
+        assert_eq!(back.len(), 1000);
+        assert_eq!(back.get(&1), d.get(&1));
+    }
+}
@@ -0,0 +1,122 @@
+use murmur_hash_rust::ConcurrentHashDict;
+fn create_test_dict(size: u32) -> ConcurrentHashDict<u32, u32> {
+    let d = ConcurrentHashDict::new(8, 64, 42);
+    for i in 0..size {
+        d.insert(i, i * 2);
+    }
+    d
+}
+
+// xorshift, good enough to scramble the stress test's operations
+fn next_rand(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(test)]
+mod concurrent_test {
+    use std::{collections::HashMap, thread};
+
+    use murmur_hash_rust::ConcurrentHashDict;
+
+    use crate::{create_test_dict, next_rand};
+
+    #[test]
+    fn test_basic_ops() {
+        let d = create_test_dict(1000);
+        assert_eq!(d.len(), 1000);
+        assert_eq!(d.get(&10), Some(20));
+        assert_eq!(d.insert(10, 0), Some(20));
+        assert_eq!(d.remove(&10), Some(0));
+        assert_eq!(d.remove(&10), None);
+        assert!(!d.contains_key(&10));
+        assert_eq!(d.len(), 999);
+    }
+
+    #[test]
+    fn test_borrowed_keys() {
+        let d: ConcurrentHashDict<String, u32> = ConcurrentHashDict::new(4, 16, 1);
+        d.insert("hello".to_string(), 1);
+        assert_eq!(d.get("hello"), Some(1));
+        assert!(d.contains_key("hello"));
+        assert_eq!(d.remove("hello"), Some(1));
+    }
+
+    #[test]
+    fn test_entry() {
+        let d: ConcurrentHashDict<&str, u32> = ConcurrentHashDict::new(4, 16, 1);
+        *d.entry("a").or_insert(0) += 5;
+        *d.entry("a").or_insert(0) += 5;
+        assert_eq!(d.get(&"a"), Some(10));
+
+        d.entry("a").and_modify(|v| *v = 1);
+        d.entry("b").and_modify(|v| *v = 1);
+        assert_eq!(d.get(&"a"), Some(1));
+        assert!(!d.contains_key(&"b"));
+
+        let mut e = d.entry("a");
+        assert_eq!(e.get(), Some(&1));
+        assert_eq!(e.remove(), Some(1));
+        assert_eq!(e.get(), None);
+    }
+
+    #[test]
+    fn test_shared_counters() {
+        let d: ConcurrentHashDict<u32, u64> = ConcurrentHashDict::new(16, 64, 3);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for i in 0..10_000u32 {
+                        *d.entry(i % 100).or_insert(0) += 1;
+                    }
+                });
+            }
+        });
+        assert_eq!(d.len(), 100);
+        for i in 0..100 {
+            assert_eq!(d.get(&i), Some(800));
+        }
+    }
+
+    #[test]
+    fn test_stress_against_model() {
+        const THREADS: u32 = 8;
+        const KEYS: u32 = 500;
+        let d: ConcurrentHashDict<u32, u32> = ConcurrentHashDict::new(16, 32, 7);
+
+        // every thread gets its own key range and mirrors its ops in a model
+        let models: Vec<HashMap<u32, u32>> = thread::scope(|s| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let d = &d;
+                    s.spawn(move || {
+                        let mut model = HashMap::new();
+                        let mut rng = 0x9e3779b97f4a7c15 ^ (t as u64 + 1);
+                        for _ in 0..20_000 {
+                            let r = next_rand(&mut rng);
+                            let key = t * KEYS + (r % KEYS as u64) as u32;
+                            let val = (r >> 32) as u32;
+                            match r % 4 {
+                                0 | 1 => assert_eq!(d.insert(key, val), model.insert(key, val)),
+                                2 => assert_eq!(d.remove(&key), model.remove(&key)),
+                                _ => assert_eq!(d.get(&key), model.get(&key).copied()),
+                            }
+                        }
+                        model
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let total: usize = models.iter().map(|m| m.len()).sum();
+        assert_eq!(d.len(), total);
+        for model in models {
+            for (k, v) in model {
+                assert_eq!(d.get(&k), Some(v));
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use murmur_hash_rust::HashDict;
+fn create_test_dict(size: u32) -> HashDict<String, Vec<u32>> {
+    let mut d: HashDict<String, Vec<u32>> = HashDict::new(101, 5);
+    for i in 0..size {
+        d.insert(format!("word{i}"), vec![i; (i % 4) as usize]);
+    }
+    d
+}
+
+#[cfg(test)]
+mod const_db_test {
+    use std::fs;
+
+    use murmur_hash_rust::{ConstDb, ConstDbError, HashDict};
+
+    use crate::create_test_dict;
+
+    #[test]
+    fn test_write_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.cdb");
+        let d = create_test_dict(5000);
+        ConstDb::write(&d, &path).unwrap();
+
+        let db: ConstDb<String, Vec<u32>> = ConstDb::open(&path).unwrap();
+        assert_eq!(db.len(), 5000);
+        assert_eq!(db.seed(), 5);
+        for (k, v) in d.iter() {
+            assert_eq!(db.get(&k).unwrap(), Some(v));
+        }
+        assert_eq!(db.get("word3").unwrap(), Some(vec![3, 3, 3]));
+        assert_eq!(db.get("missing").unwrap(), None);
+        assert!(db.contains_key("word0").unwrap());
+        assert!(!db.contains_key("word5000").unwrap());
+    }
+
+    #[test]
+    fn test_get_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bytes.cdb");
+        let d = murmur_hash_rust::hash_dict![4, 1, b"k".to_vec() => 7u8];
+        ConstDb::write(&d, &path).unwrap();
+
+        let db: ConstDb<Vec<u8>, u8> = ConstDb::open(&path).unwrap();
+        assert_eq!(db.get_bytes(&b"k"[..]).unwrap(), Some(&[7u8][..]));
+    }
+
+    #[test]
+    fn test_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.cdb");
+        let d: HashDict<String, u32> = HashDict::new(10, 1);
+        ConstDb::write(&d, &path).unwrap();
+
+        let db: ConstDb<String, u32> = ConstDb::open(&path).unwrap();
+        assert!(db.is_empty());
+        assert_eq!(db.get("a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shared.cdb");
+        ConstDb::write(&create_test_dict(1000), &path).unwrap();
+
+        let db: ConstDb<String, Vec<u32>> = ConstDb::open(&path).unwrap();
+        std::thread::scope(|s| {
+            for t in 0..4u32 {
+                let db = &db;
+                s.spawn(move || {
+                    for i in (t..1000).step_by(4) {
+                        let v = db.get(&format!("word{i}")).unwrap().unwrap();
+                        assert_eq!(v.len(), (i % 4) as usize);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_failed_write_removes_tmp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // renaming a file over a directory fails after the tmp file exists
+        let path = dir.path().join("taken");
+        fs::create_dir(&path).unwrap();
+
+        assert!(matches!(
+            ConstDb::write(&create_test_dict(10), &path),
+            Err(ConstDbError::Io(_))
+        ));
+        assert!(!dir.path().join("taken.tmp").exists());
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn test_bad_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.cdb");
+        ConstDb::write(&create_test_dict(100), &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let open = |bytes: &[u8]| {
+            let path = dir.path().join("bad.cdb");
+            fs::write(&path, bytes).unwrap();
+            ConstDb::<String, Vec<u32>>::open(&path).map(|_| ())
+        };
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert!(matches!(open(&bad), Err(ConstDbError::BadMagic)));
+
+        let mut bad = bytes.clone();
+        bad[4] = 9;
+        assert!(matches!(
+            open(&bad),
+            Err(ConstDbError::UnsupportedVersion(9))
+        ));
+
+        let mut bad = bytes.clone();
+        bad[13] ^= 1;
+        assert!(matches!(open(&bad), Err(ConstDbError::CorruptHeader)));
+
+        assert!(matches!(open(&bytes[..20]), Err(ConstDbError::Truncated)));
+        assert!(matches!(open(&bytes[..100]), Err(ConstDbError::Truncated)));
+    }
+
+    #[test]
+    fn test_truncated_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.cdb");
+        ConstDb::write(&create_test_dict(100), &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+
+        let db: ConstDb<String, Vec<u32>> = ConstDb::open(&path).unwrap();
+        let errors = (0..100)
+            .filter(|i| matches!(db.get(&format!("word{i}")), Err(ConstDbError::Corrupt(_))))
+            .count();
+        assert!(errors > 0);
+    }
+
+    #[test]
+    fn test_slot_offset_overflow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.cdb");
+        ConstDb::write(&create_test_dict(100), &path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+
+        // the directory starts after the 36 byte header, 16 bytes a slot
+        let slots = u64::from_le_bytes(bytes[12..20].try_into().unwrap()) as usize;
+        for slot in 0..slots {
+            let at = 36 + slot * 16;
+            bytes[at..at + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        }
+        fs::write(&path, &bytes).unwrap();
+
+        let db: ConstDb<String, Vec<u32>> = ConstDb::open(&path).unwrap();
+        for i in 0..100 {
+            assert!(matches!(
+                db.get(&format!("word{i}")),
+                Err(ConstDbError::Corrupt(_))
+            ));
+        }
+    }
+}
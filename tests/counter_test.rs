@@ -0,0 +1,86 @@
+use murmur_hash_rust::HashCounter;
+// Letter counts of a word
+fn letters(word: &str) -> HashCounter<char> {
+    let mut counter = HashCounter::new(16, 42);
+    counter.extend(word.chars());
+    counter
+}
+
+#[cfg(test)]
+mod counter_test {
+    use murmur_hash_rust::HashCounter;
+
+    use crate::letters;
+
+    #[test]
+    fn test_add_subtract() {
+        let mut c: HashCounter<&str> = HashCounter::new(8, 1);
+        c.add("a").add("a").add_n("b", 5).add_n("c", 0);
+        assert_eq!(c.get("a"), 2);
+        assert_eq!(c.get("c"), 0);
+        assert_eq!(c.len(), 2);
+        assert_eq!(c.total(), 7);
+
+        assert_eq!(c.subtract("b", 2), 3);
+        assert_eq!(c.subtract("a", 10), 0);
+        assert_eq!(c.subtract("z", 1), 0);
+        assert_eq!(c.len(), 1);
+        assert_eq!(c.total(), 3);
+        assert_eq!(c.remove("b"), 3);
+        assert!(c.is_empty());
+        assert_eq!(c.total(), 0);
+    }
+
+    #[test]
+    fn test_most_common() {
+        let c = letters("mississippi");
+        assert_eq!(c.most_common(2), vec![(&'i', 4), (&'s', 4)]);
+        assert_eq!(
+            c.most_common(10),
+            vec![(&'i', 4), (&'s', 4), (&'p', 2), (&'m', 1)]
+        );
+        assert!(c.most_common(0).is_empty());
+    }
+
+    #[test]
+    fn test_most_common_matches_sort() {
+        let mut c: HashCounter<u32> = HashCounter::new(64, 9);
+        for i in 0..500u32 {
+            c.add_n(i, (i * 7919 % 101) as usize);
+        }
+        let mut all: Vec<(u32, usize)> = c.iter().map(|(k, n)| (*k, n)).collect();
+        all.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let top: Vec<(u32, usize)> = c
+            .most_common(25)
+            .into_iter()
+            .map(|(k, n)| (*k, n))
+            .collect();
+        assert_eq!(top, all[..25]);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = letters("hello");
+        let b = letters("world");
+
+        let sum = &a + &b;
+        assert_eq!(sum.get(&'l'), 3);
+        assert_eq!(sum.get(&'o'), 2);
+        assert_eq!(sum.total(), 10);
+
+        let diff = &a - &b;
+        assert_eq!(diff, letters("hel"));
+        assert_eq!(diff.total(), 3);
+
+        let mut c = a.clone();
+        c += &b;
+        assert_eq!(c, sum);
+        c -= &b;
+        assert_eq!(c, a);
+
+        let mut merged = letters("");
+        merged.merge(&a);
+        merged.merge(&a);
+        assert_eq!(merged.get(&'l'), 4);
+    }
+}
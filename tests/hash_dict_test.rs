@@ -4,7 +4,7 @@ fn create_test_dict_i32(size: i32) -> HashDict<i32, i32> {
     for i in 0..size {
         d.insert(i, i.pow(2));
     }
-    return d;
+    d
 }
 
 #[cfg(test)]
@@ -135,10 +135,237 @@ mod hash_dict_test {
         let key = 100;
         println!("{}", dict.get(&key).unwrap());
 
-        dict.get_mut(&key, |v| {
+        if let Some(v) = dict.get_mut(&key) {
             *v = 0;
-        });
+        }
 
         println!("{}", dict.get(&key).unwrap());
+        assert_eq!(dict.get(&key), Some(0));
+    }
+
+    #[test]
+    fn test_insert_replaces() {
+        let mut d: HashDict<i32, i32> = HashDict::new(10, 42);
+        d.insert(1, 1).insert(1, 2);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d.get(&1), Some(2));
+    }
+
+    #[test]
+    fn test_collect() {
+        let d: HashDict<i32, i32> = (0..100).map(|i| (i, i * 2)).collect();
+        assert_eq!(d.len(), 100);
+        assert_eq!(d.get(&50), Some(100));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut d = create_test_dict_i32(10);
+        d.extend(vec![(5, -5), (20, 400)]);
+        assert_eq!(d.len(), 11);
+        assert_eq!(d.get(&5), Some(-5));
+        assert_eq!(d.get(&20), Some(400));
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let d = create_test_dict_i32(50);
+        let mut count = 0;
+        for (k, v) in &d {
+            assert_eq!(v, k.pow(2));
+            count += 1;
+        }
+        assert_eq!(count, 50);
+        assert_eq!(d.into_iter().count(), 50);
+    }
+
+    #[test]
+    fn test_into_iter_moves() {
+        // not Clone, so the entries can only come out by moving
+        #[derive(Debug, PartialEq)]
+        struct Owned(String);
+
+        let mut d: HashDict<i32, Owned> = HashDict::new(8, 42);
+        for i in (0..20).rev() {
+            d.insert(i, Owned(i.to_string()));
+        }
+        let entries: Vec<(i32, Owned)> = d.into_iter().collect();
+        assert_eq!(entries.len(), 20);
+        assert!(entries
+            .iter()
+            .enumerate()
+            .all(|(i, (k, v))| *k == i as i32 && v.0 == k.to_string()));
+    }
+
+    #[test]
+    fn test_index() {
+        let d = create_test_dict_i32(100);
+        assert_eq!(d[&7], 49);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_missing() {
+        let d = create_test_dict_i32(100);
+        let _ = d[&1000];
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = create_test_dict_i32(100);
+        let b: HashDict<i32, i32> = (0..100).rev().map(|i: i32| (i, i.pow(2))).collect();
+        assert_eq!(a, b);
+
+        let mut c = a.clone();
+        c.insert(3, 0);
+        assert_ne!(a, c);
+        assert_eq!(a.get(&3), Some(9));
+    }
+
+    #[test]
+    fn test_debug() {
+        let d = hash_dict![16, 42, 2 => "b", 1 => "a"];
+        assert_eq!(format!("{:?}", d), r#"{1: "a", 2: "b"}"#);
+    }
+
+    #[test]
+    fn test_borrowed_str_lookup() {
+        let mut dict: HashDict<String, i32> = HashDict::new(10, 42);
+        dict.insert("Hello".to_string(), 1)
+            .insert("World!".to_string(), 2);
+
+        assert_eq!(dict.get("Hello"), Some(1));
+        assert_eq!(dict["World!"], 2);
+        assert_eq!(dict.get("missing"), None);
+
+        dict.update("Hello", |v| *v += 10);
+        assert_eq!(dict.pop("Hello"), Some(11));
+        dict.delete("World!");
+        assert!(dict.is_empty());
+    }
+
+    #[test]
+    fn test_borrowed_bytes_lookup() {
+        let mut dict: HashDict<Vec<u8>, usize> = HashDict::new(10, 42);
+        dict.insert(b"abc".to_vec(), 3).insert(vec![], 0);
+
+        let key: &[u8] = b"abc";
+        assert_eq!(dict.get(key), Some(3));
+        assert_eq!(dict.get(&[][..]), Some(0));
+        assert_eq!(dict.get(&b"abd"[..]), None);
+    }
+
+    #[test]
+    fn test_get_ref_without_clone() {
+        struct NoClone(i32);
+        impl std::fmt::Debug for NoClone {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "NoClone({})", self.0)
+            }
+        }
+
+        let mut dict: HashDict<&str, NoClone> = HashDict::new(8, 42);
+        dict.insert("a", NoClone(1)).insert("b", NoClone(2));
+
+        assert_eq!(dict.get_ref(&"a").map(|v| v.0), Some(1));
+        dict.get_mut(&"b").unwrap().0 = 20;
+        assert_eq!(dict[&"b"].0, 20);
+        assert_eq!(dict.pop(&"a").map(|v| v.0), Some(1));
+        assert!(dict.get_ref(&"a").is_none());
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let d = create_test_dict_i32(100);
+        assert!(d.contains_key(&99));
+        assert!(!d.contains_key(&100));
+    }
+
+    #[test]
+    fn test_get_key_value() {
+        let dict = hash_dict![10, 42, "key".to_string() => 5];
+        assert_eq!(dict.get_key_value("key"), Some((&"key".to_string(), &5)));
+        assert_eq!(dict.get_key_value("nope"), None);
+    }
+
+    #[test]
+    fn test_remove_entry() {
+        let mut d = create_test_dict_i32(1000);
+        for i in 0..1000 {
+            assert_eq!(d.remove_entry(&i), Some((i, i.pow(2))));
+        }
+        assert_eq!(d.remove_entry(&0), None);
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut d = create_test_dict_i32(500);
+        d.clear();
+        assert!(d.is_empty());
+        assert!(!d.contains_key(&1));
+        d.insert(1, 2);
+        assert_eq!(d.get(&1), Some(2));
+    }
+
+    #[test]
+    fn test_retain_and_extract_if() {
+        // few buckets, so every tree holds many entries and gets rebuilt
+        let mut d: HashDict<i32, i32> = HashDict::new(8, 42);
+        for i in 0..1000 {
+            d.insert(i, i);
+        }
+        d.retain(|k, v| {
+            *v += 1;
+            k % 2 == 0
+        });
+        assert_eq!(d.len(), 500);
+        assert_eq!(d.get(&10), Some(11));
+        assert_eq!(d.get(&11), None);
+
+        let mut taken = d.extract_if(|k, _| k % 10 == 0);
+        taken.sort();
+        assert_eq!(
+            taken,
+            (0..1000)
+                .step_by(10)
+                .map(|i| (i, i + 1))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(d.len(), 400);
+        assert!(d.extract_if(|_, _| false).is_empty());
+
+        // the rebuilt trees still take inserts and deletes
+        for i in 0..1000 {
+            d.insert(i, -i);
+        }
+        for i in (0..1000).step_by(3) {
+            d.delete(&i);
+        }
+        assert_eq!(d.len(), 666);
+        assert_eq!(d.get(&1), Some(-1));
+    }
+
+    #[test]
+    fn test_remove_many() {
+        let mut d: HashDict<String, usize> = HashDict::new(4, 7);
+        for i in 0..100 {
+            d.insert(i.to_string(), i);
+        }
+        let keys = ["3", "14", "15", "92", "not there", "3"];
+        let mut removed = d.remove_many(keys.iter().copied());
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                ("14".to_string(), 14),
+                ("15".to_string(), 15),
+                ("3".to_string(), 3),
+                ("92".to_string(), 92),
+            ]
+        );
+        assert_eq!(d.len(), 96);
+        assert!(!d.contains_key("14"));
+        assert!(d.contains_key("65"));
     }
 }
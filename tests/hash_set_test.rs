@@ -0,0 +1,79 @@
+use murmur_hash_rust::HashSetDict;
+// Set of the given numbers, collected through `FromIterator`
+fn set_of(keys: impl IntoIterator<Item = u32>) -> HashSetDict<u32> {
+    keys.into_iter().collect()
+}
+
+fn sorted<'a>(keys: impl Iterator<Item = &'a u32>) -> Vec<u32> {
+    let mut keys: Vec<u32> = keys.copied().collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+mod hash_set_test {
+    use murmur_hash_rust::{HashSetDict, SmallBucket};
+
+    use crate::{set_of, sorted};
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut s: HashSetDict<String> = HashSetDict::new(16, 42);
+        assert!(s.insert("apple".to_string()));
+        assert!(s.insert("pear".to_string()));
+        assert!(!s.insert("apple".to_string()));
+        assert_eq!(s.len(), 2);
+        assert!(s.contains("apple"));
+        assert!(!s.contains("fig"));
+        assert!(s.remove("apple"));
+        assert!(!s.remove("apple"));
+        assert_eq!(s.len(), 1);
+    }
+
+    #[test]
+    fn test_iter_borrows_every_key() {
+        let mut s: HashSetDict<u32, SmallBucket<u32, ()>> = HashSetDict::with_bucket_type(7, 3);
+        s.extend(0..100);
+        let iter = s.iter();
+        assert_eq!(iter.len(), 100);
+        assert_eq!(sorted(iter), (0..100).collect::<Vec<_>>());
+        assert_eq!((&s).into_iter().count(), 100);
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let a = set_of(0..10);
+        let b = set_of(5..15);
+        assert_eq!(sorted(a.union(&b)), (0..15).collect::<Vec<_>>());
+        assert_eq!(sorted(a.intersection(&b)), (5..10).collect::<Vec<_>>());
+        assert_eq!(sorted(b.intersection(&a)), (5..10).collect::<Vec<_>>());
+        assert_eq!(sorted(a.difference(&b)), (0..5).collect::<Vec<_>>());
+        assert_eq!(sorted(b.difference(&a)), (10..15).collect::<Vec<_>>());
+        assert_eq!(
+            sorted(a.symmetric_difference(&b)),
+            (0..5).chain(10..15).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_subset_superset_disjoint() {
+        let all = set_of(0..20);
+        let evens = set_of((0..20).step_by(2));
+        let odds = set_of((1..20).step_by(2));
+        assert!(evens.is_subset(&all));
+        assert!(all.is_superset(&odds));
+        assert!(!all.is_subset(&evens));
+        assert!(evens.is_disjoint(&odds));
+        assert!(!evens.is_disjoint(&all));
+        assert!(set_of([]).is_subset(&evens));
+    }
+
+    #[test]
+    fn test_equality_ignores_layout() {
+        let mut a: HashSetDict<u32> = HashSetDict::new(3, 1);
+        a.extend([1, 2, 3]);
+        assert_eq!(a, set_of([3, 2, 1]));
+        assert_ne!(a, set_of([1, 2]));
+        assert_eq!(format!("{:?}", set_of([7])), "{7}");
+    }
+}
@@ -1,21 +1,45 @@
-// #[cfg(test)]
-// mod hash_test {
-//     use murmur_hash_rust::{hash_anything, hash_mumur3};
+#[cfg(test)]
+mod hash_test {
+    use murmur_hash_rust::{hash_anything, hash_mumur3};
 
-//     #[test]
-//     fn test_hash() {
-//         let key = "Hello World!".to_string();
-//         let key2 = [32u8];
-//         let seed = 42;
-//         let hash_res = hash_mumur3(key, seed);
-//         let hash_all_res = hash_anything(&key2, seed);
+    #[test]
+    fn test_hash() {
+        let key = "Hello World!".to_string();
+        let key2 = [32u8];
+        let seed = 42;
+        let hash_res = hash_mumur3(key, seed);
+        let hash_all_res = hash_anything(&key2, seed);
 
-//         if let Ok(hash_num) = hash_res {
-//             println!("{:?}", hash_num);
-//         }
+        if let Ok(hash_num) = hash_res {
+            println!("{:?}", hash_num);
+        }
 
-//         if let Ok(hash_num) = hash_all_res {
-//             println!("{:?}", hash_num)
-//         }
-//     }
-// }
+        if let Ok(hash_num) = hash_all_res {
+            println!("{:?}", hash_num)
+        }
+    }
+
+    #[test]
+    fn test_reference_values() {
+        // MurmurHash3_x64_128, h2 in the high half and h1 in the low half
+        assert_eq!(hash_mumur3(b"", 0).unwrap(), 0);
+        assert_eq!(
+            hash_mumur3(b"hello", 0).unwrap(),
+            121118445609844952839898260755277781762
+        );
+        assert_eq!(
+            hash_mumur3(b"The quick brown fox jumps over the lazy dog", 7).unwrap(),
+            195012876215891841133088060326725324932
+        );
+    }
+
+    #[test]
+    fn test_whole_blocks() {
+        // keys that are a multiple of 16 bytes long have no tail
+        let a = hash_mumur3(b"0123456789abcdef", 42).unwrap();
+        let b = hash_mumur3(b"0123456789abcdeg", 42).unwrap();
+        assert_eq!(a, 327015121000391885967322798011814032036);
+        assert_ne!(a, b);
+        assert_ne!(hash_mumur3(b"", 1).unwrap(), hash_mumur3(b"", 2).unwrap());
+    }
+}
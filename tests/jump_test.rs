@@ -0,0 +1,49 @@
+use murmur_hash_rust::jump_hash;
+// Shard of every key from 0 to `keys`
+fn shards(count: u32, keys: u64) -> Vec<u32> {
+    (0..keys)
+        .map(|k| jump_hash(&k, count, 42).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod jump_test {
+    use murmur_hash_rust::jump_hash;
+
+    use crate::shards;
+
+    #[test]
+    fn test_range() {
+        assert_eq!(jump_hash("key", 0, 42), None);
+        assert_eq!(jump_hash("key", 1, 42), Some(0));
+        assert!(shards(7, 1000).iter().all(|s| *s < 7));
+        assert_eq!(jump_hash("key", 7, 42), jump_hash("key", 7, 42));
+    }
+
+    #[test]
+    fn test_balance() {
+        let keys = shards(10, 20000);
+        for shard in 0..10 {
+            let count = keys.iter().filter(|s| **s == shard).count();
+            // 2000 each if perfectly even
+            assert!((1800..2200).contains(&count), "{shard} got {count}");
+        }
+    }
+
+    #[test]
+    fn test_growing_moves_keys_to_new_shard_only() {
+        let mut before = shards(10, 20000);
+        for count in 11..=15 {
+            let after = shards(count, 20000);
+            let moved: Vec<_> = before.iter().zip(&after).filter(|(a, b)| a != b).collect();
+            assert!(moved.iter().all(|(_, b)| **b == count - 1));
+            let ideal = 20000 / count as usize;
+            assert!(
+                moved.len().abs_diff(ideal) < ideal / 5,
+                "{} moved",
+                moved.len()
+            );
+            before = after;
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    cell::Cell,
+    fmt::{self, Debug},
+    rc::Rc,
+};
+
+// Counts how many copies of itself are alive, so a test can check that a
+// collection dropped everything it was handed
+struct Tracked {
+    id: u32,
+    live: Rc<Cell<usize>>,
+}
+
+fn tracked(id: u32, live: &Rc<Cell<usize>>) -> Tracked {
+    live.set(live.get() + 1);
+    Tracked {
+        id,
+        live: live.clone(),
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.live.set(self.live.get() - 1);
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        tracked(self.id, &self.live)
+    }
+}
+
+impl Debug for Tracked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tracked({})", self.id)
+    }
+}
+
+impl PartialEq for Tracked {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl PartialOrd for Tracked {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.id.partial_cmp(&other.id)
+    }
+}
+
+#[cfg(test)]
+mod leak_test {
+    use std::{cell::Cell, rc::Rc};
+
+    use murmur_hash_rust::{HashDict, RbTree};
+
+    use crate::{tracked, Tracked};
+
+    fn tracked_tree(size: u32, live: &Rc<Cell<usize>>) -> RbTree<Tracked, Tracked> {
+        let mut t = RbTree::new();
+        for i in 0..size {
+            t.insert(tracked(i, live), tracked(i, live));
+        }
+        t
+    }
+
+    #[test]
+    fn test_tree_drop() {
+        let live = Rc::new(Cell::new(0));
+        let t = tracked_tree(1000, &live);
+        assert_eq!(live.get(), 2000);
+        drop(t);
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn test_tree_clear() {
+        let live = Rc::new(Cell::new(0));
+        let mut t = tracked_tree(1000, &live);
+        t.clear();
+        assert_eq!(live.get(), 0);
+        assert_eq!(t.capacity(), 0);
+
+        t.insert(tracked(1, &live), tracked(1, &live));
+        assert_eq!(live.get(), 2);
+    }
+
+    #[test]
+    fn test_tree_delete() {
+        let live = Rc::new(Cell::new(0));
+        let mut t = tracked_tree(1000, &live);
+        for i in 0..500 {
+            // the lookup key is dropped along with the removed entry
+            t.delete(tracked(i, &live));
+        }
+        assert_eq!(live.get(), 1000);
+
+        let removed = t.remove_entry(&tracked(700, &live)).unwrap();
+        assert_eq!(removed.0.id, 700);
+        drop(removed);
+        assert_eq!(live.get(), 998);
+    }
+
+    #[test]
+    fn test_tree_clone_and_shrink() {
+        let live = Rc::new(Cell::new(0));
+        let mut t = tracked_tree(1000, &live);
+        let copy = t.clone();
+        assert_eq!(live.get(), 4000);
+
+        for i in 0..900 {
+            t.remove_entry(&tracked(i, &live));
+        }
+        let cap = t.capacity();
+        t.shrink_to_fit();
+        assert!(t.capacity() < cap);
+        assert_eq!(
+            t.iter_ref().map(|(k, _)| k.id).collect::<Vec<_>>(),
+            (900..1000).collect::<Vec<_>>()
+        );
+        assert_eq!(live.get(), 2200);
+
+        drop(copy);
+        drop(t);
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn test_dict_drop_and_pop() {
+        let live = Rc::new(Cell::new(0));
+        let mut d: HashDict<u32, Tracked> = HashDict::new(64, 42);
+        for i in 0..1000 {
+            d.insert(i, tracked(i, &live));
+        }
+        // replacing a value drops the old one
+        d.insert(5, tracked(5, &live));
+        assert_eq!(live.get(), 1000);
+
+        for i in 0..100 {
+            d.delete(&i);
+        }
+        assert_eq!(d.pop(&100).map(|v| v.id), Some(100));
+        assert_eq!(live.get(), 899);
+
+        for i in 101..1000 {
+            d.delete(&i);
+        }
+        assert!(d.is_empty());
+        assert_eq!(live.get(), 0);
+
+        d.insert(1, tracked(1, &live));
+        drop(d);
+        assert_eq!(live.get(), 0);
+    }
+}
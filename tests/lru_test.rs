@@ -0,0 +1,146 @@
+use murmur_hash_rust::LruDict;
+use serde::Serialize;
+// Keys of the cache from most to least recently used
+fn recency(cache: &LruDict<u32, String>) -> Vec<u32> {
+    cache.iter().map(|(k, _)| *k).collect()
+}
+
+// Not Clone, and the tag is left out of the encoding, so keys that differ
+// only in their tag hash the same
+#[derive(Serialize, PartialEq, PartialOrd, Debug)]
+struct Tagged {
+    id: u32,
+    #[serde(skip)]
+    tag: u32,
+}
+
+#[cfg(test)]
+mod lru_test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use murmur_hash_rust::LruDict;
+
+    use crate::{recency, Tagged};
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache: LruDict<u32, String> = LruDict::new(3, 42);
+        for i in 0..3 {
+            cache.put(i, i.to_string());
+        }
+        assert_eq!(recency(&cache), vec![2, 1, 0]);
+
+        // get marks as used, peek doesn't
+        assert_eq!(cache.get(&0).map(String::as_str), Some("0"));
+        assert_eq!(cache.peek(&1).map(String::as_str), Some("1"));
+        assert_eq!(recency(&cache), vec![0, 2, 1]);
+
+        cache.put(3, "3".to_string());
+        assert!(!cache.contains_key(&1));
+        assert_eq!(recency(&cache), vec![3, 0, 2]);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_put_replaces() {
+        let mut cache: LruDict<u32, String> = LruDict::new(2, 42);
+        assert_eq!(cache.put(1, "a".to_string()), None);
+        cache.put(2, "b".to_string());
+        assert_eq!(cache.put(1, "c".to_string()), Some("a".to_string()));
+        assert_eq!(recency(&cache), vec![1, 2]);
+        cache.get_mut(&2).unwrap().push('!');
+        assert_eq!(cache.pop(&2), Some("b!".to_string()));
+        assert_eq!(cache.pop(&2), None);
+        assert_eq!(recency(&cache), vec![1]);
+    }
+
+    #[test]
+    fn test_eviction_callback() {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let mut cache: LruDict<u32, u32> = LruDict::new(10, 7);
+        let log = Rc::clone(&evicted);
+        cache.set_on_evict(move |k, v| log.borrow_mut().push((k, v)));
+
+        for i in 0..25 {
+            cache.put(i, i * 2);
+        }
+        // replaced and popped entries are not evictions
+        cache.put(24, 0);
+        cache.pop(&23);
+        let expected: Vec<(u32, u32)> = (0..15).map(|i| (i, i * 2)).collect();
+        assert_eq!(*evicted.borrow(), expected);
+        assert_eq!(cache.len(), 9);
+    }
+
+    #[test]
+    fn test_weighted_capacity() {
+        let mut cache: LruDict<&str, Vec<u8>> =
+            LruDict::with_weigher(100, 42, |_, v: &Vec<u8>| v.len());
+        cache.put("a", vec![0; 40]);
+        cache.put("b", vec![0; 40]);
+        assert_eq!(cache.weight(), 80);
+        cache.put("c", vec![0; 30]);
+        assert!(!cache.contains_key("a"));
+        assert_eq!(cache.weight(), 70);
+
+        // growing an entry on put evicts others to make room
+        cache.put("c", vec![0; 90]);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.weight(), 90);
+
+        // an entry heavier than the whole cache doesn't stay
+        cache.put("d", vec![0; 101]);
+        assert!(cache.is_empty());
+        assert_eq!(cache.weight(), 0);
+    }
+
+    #[test]
+    fn test_many_light_entries() {
+        // weightless entries never fill the cache, so the index has to keep
+        // growing with the entry count rather than the capacity
+        let mut cache: LruDict<u32, u32> = LruDict::with_weigher(1, 42, |_, _| 0);
+        for i in 0..50_000 {
+            cache.put(i, i);
+        }
+        assert_eq!(cache.len(), 50_000);
+        assert!((0..50_000).all(|i| cache.get(&i) == Some(&i)));
+        assert_eq!(cache.iter().next(), Some((&49_999, &49_999)));
+        assert!((0..50_000).step_by(2).all(|i| cache.pop(&i) == Some(i)));
+        assert_eq!(cache.len(), 25_000);
+        assert!(!cache.contains_key(&0) && cache.contains_key(&1));
+    }
+
+    #[test]
+    fn test_reuses_nodes() {
+        let mut cache: LruDict<u32, String> = LruDict::new(4, 42);
+        for i in 0..10_000 {
+            cache.put(i, i.to_string());
+            if i % 3 == 0 {
+                cache.get(&(i / 2));
+            }
+        }
+        assert_eq!(recency(&cache), vec![9999, 9998, 9997, 9996]);
+    }
+
+    #[test]
+    fn test_keys_sharing_a_hash() {
+        let key = |id, tag| Tagged { id, tag };
+        let mut cache: LruDict<Tagged, u32> = LruDict::new(3, 42);
+        for tag in 0..3 {
+            cache.put(key(1, tag), tag);
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.peek(&key(1, 1)), Some(&1));
+
+        // out of the middle of the chain, then the head
+        assert_eq!(cache.pop(&key(1, 1)), Some(1));
+        cache.get(&key(1, 0));
+        cache.put(key(2, 0), 9);
+        cache.put(key(3, 0), 9);
+        assert!(!cache.contains_key(&key(1, 2)));
+        assert_eq!(cache.peek(&key(1, 0)), Some(&0));
+        assert_eq!(cache.pop(&key(1, 0)), Some(0));
+        assert!(!cache.contains_key(&key(1, 0)));
+        assert_eq!(cache.len(), 2);
+    }
+}
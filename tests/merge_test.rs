@@ -0,0 +1,78 @@
+use murmur_hash_rust::HashDict;
+// Word counts of a text
+fn counts(text: &str) -> HashDict<String, u32> {
+    let mut d = HashDict::new(32, 42);
+    for word in text.split_whitespace() {
+        match d.get_mut(word) {
+            Some(n) => *n += 1,
+            None => {
+                d.insert(word.to_string(), 1);
+            }
+        }
+    }
+    d
+}
+
+#[cfg(test)]
+mod merge_test {
+    use murmur_hash_rust::{HashDict, HashDictDiff, SmallBucket};
+
+    use crate::counts;
+
+    #[test]
+    fn test_merge_with() {
+        let mut a = counts("the cat and the hat");
+        let b = counts("the dog and the log and the frog");
+        a.merge_with(b, |existing, incoming| *existing += incoming);
+        assert_eq!(
+            a,
+            counts("the cat and the hat the dog and the log and the frog")
+        );
+    }
+
+    #[test]
+    fn test_merge_across_bucket_types() {
+        let mut a: HashDict<u32, &str> = HashDict::new(4, 1);
+        let mut b: HashDict<u32, &str, SmallBucket<_, _>> = HashDict::with_bucket_type(64, 9);
+        a.insert(1, "a1").insert(2, "a2");
+        b.insert(2, "b2").insert(3, "b3");
+        // keep what we had
+        a.merge_with(b, |_, _| {});
+        assert_eq!(
+            a.iter().collect::<Vec<_>>(),
+            vec![(1, "a1"), (2, "a2"), (3, "b3")]
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = counts("x y y z");
+        let b = counts("y z z w");
+        let diff = a.diff(&b);
+        assert_eq!(
+            diff,
+            HashDictDiff {
+                added: vec![("w".to_string(), 1)],
+                removed: vec!["x".to_string()],
+                changed: vec![("y".to_string(), 1), ("z".to_string(), 2)],
+            }
+        );
+        assert_eq!(diff.len(), 4);
+        assert!(a.diff(&a.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_apply_diff_reconciles_replicas() {
+        let mut replica = counts("one two three three");
+        let primary = counts("two three four four four");
+        let diff = replica.diff(&primary);
+
+        // ships over the wire like any serde type
+        let wire = serde_json::to_string(&diff).unwrap();
+        let diff: HashDictDiff<String, u32> = serde_json::from_str(&wire).unwrap();
+
+        replica.apply_diff(diff);
+        assert_eq!(replica, primary);
+        assert!(replica.diff(&primary).is_empty());
+    }
+}
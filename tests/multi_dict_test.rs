@@ -0,0 +1,84 @@
+use murmur_hash_rust::HashMultiDict;
+// Three colors per fruit, in a table small enough to share buckets
+fn fruit_colors() -> HashMultiDict<String, &'static str> {
+    let mut d = HashMultiDict::new(2, 42);
+    for fruit in ["apple", "pear", "plum"] {
+        for color in ["green", "red", "yellow"] {
+            d.insert_multi(fruit.to_string(), color);
+        }
+    }
+    d
+}
+
+#[cfg(test)]
+mod multi_dict_test {
+    use murmur_hash_rust::HashMultiDict;
+
+    use crate::fruit_colors;
+
+    #[test]
+    fn test_get_all_in_insertion_order() {
+        let mut d: HashMultiDict<&str, u32> = HashMultiDict::new(8, 1);
+        d.insert_multi("a", 3)
+            .insert_multi("a", 1)
+            .insert_multi("a", 2);
+        assert_eq!(d.get_all("a").copied().collect::<Vec<_>>(), vec![3, 1, 2]);
+        assert_eq!(d.get_all("b").count(), 0);
+        assert_eq!(d.count("a"), 3);
+        assert_eq!(d.count("b"), 0);
+    }
+
+    #[test]
+    fn test_remove_one() {
+        let mut d = fruit_colors();
+        assert_eq!(d.remove_one("pear"), Some("green"));
+        assert_eq!(
+            d.get_all("pear").collect::<Vec<_>>(),
+            vec![&"red", &"yellow"]
+        );
+        assert_eq!(d.remove_one("pear"), Some("red"));
+        assert_eq!(d.remove_one("pear"), Some("yellow"));
+        assert_eq!(d.remove_one("pear"), None);
+        assert!(!d.contains_key("pear"));
+        assert_eq!(d.len(), 6);
+        assert_eq!(d.key_count(), 2);
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let mut d = fruit_colors();
+        assert_eq!(d.remove_all("apple"), vec!["green", "red", "yellow"]);
+        assert!(d.remove_all("apple").is_empty());
+        assert_eq!(d.count("plum"), 3);
+        assert_eq!(d.len(), 6);
+        d.remove_all("plum");
+        d.remove_all("pear");
+        assert!(d.is_empty());
+        assert_eq!(d.key_count(), 0);
+    }
+
+    #[test]
+    fn test_collect() {
+        let d: HashMultiDict<u32, u32> = (0..30).map(|i| (i % 3, i)).collect();
+        assert_eq!(d.key_count(), 3);
+        assert_eq!(d.len(), 30);
+        assert_eq!(
+            d.get_all(&1).copied().collect::<Vec<_>>(),
+            (1..30).step_by(3).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_drain_with_remove_one() {
+        let mut d: HashMultiDict<&str, u32> = HashMultiDict::new(8, 1);
+        for i in 0..10_000 {
+            d.insert_multi("k", i);
+        }
+        assert_eq!(d.get_all("k").next_back(), Some(&9_999));
+        for i in 0..10_000 {
+            assert_eq!(d.remove_one("k"), Some(i));
+        }
+        assert_eq!(d.remove_one("k"), None);
+        assert!(d.is_empty() && !d.contains_key("k"));
+    }
+}
@@ -4,7 +4,7 @@ fn create_test_tree(t_size: i32) -> RbTree<i32, i32> {
     for i in 0..t_size {
         t.insert(i, i % 2);
     }
-    return t;
+    t
 }
 
 #[cfg(test)]
@@ -35,7 +35,7 @@ mod rb_tree {
         println!("{}", t.len());
         t.delete(132).delete(10).delete(255).delete(1);
         println!("{}", t.len());
-        assert_eq!(t.key_exist(132), false);
+        assert!(!t.key_exist(132));
     }
 
     #[test]
@@ -109,10 +109,171 @@ mod rb_tree {
         let key = 3;
         println!("{}", t.get(&key).unwrap());
 
-        t.get_mut(&key, |value| {
+        if let Some(value) = t.get_mut(&key) {
             *value *= 100;
-        });
+        }
 
         println!("{}", t.get(&key).unwrap());
+        assert_eq!(t.get_ref(&key), Some(&100));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut t = create_test_tree(5);
+        t.update(&3, |value| *value += 1);
+        assert_eq!(t.get(&3), Some(2));
+    }
+
+    #[test]
+    fn test_remove_entry() {
+        let size = 500;
+        let mut t = create_test_tree(size);
+        for i in (0..size).rev() {
+            assert_eq!(t.remove_entry(&i), Some((i, i % 2)));
+            assert!(!t.contains_key(&i));
+        }
+        assert_eq!(t.remove_entry(&0), None);
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn test_clone_is_deep() {
+        let t = create_test_tree(50);
+        let mut copy = t.clone();
+        *copy.get_mut(&3).unwrap() = 100;
+        copy.delete(4);
+
+        assert_eq!(t.get(&3), Some(1));
+        assert!(t.key_exist(4));
+        assert_eq!(copy.get(&3), Some(100));
+        assert_eq!(copy.len(), 49);
+    }
+
+    #[test]
+    fn test_delete_scrambled() {
+        for size in [10, 97, 1000, 4099] {
+            let mut t: RbTree<i64, i64> = RbTree::new();
+            for i in 0..size {
+                t.insert((i * 7919) % size, i);
+            }
+            for i in 0..size {
+                let key = (i * 104729 + 13) % size;
+                assert!(t.remove_entry(&key).is_some());
+                assert!(!t.contains_key(&key));
+            }
+            assert!(t.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_iter_ref() {
+        let mut t: RbTree<String, usize> = RbTree::new();
+        for w in ["pear", "apple", "fig", "kiwi"] {
+            t.insert(w.to_string(), w.len());
+        }
+        let items: Vec<(&String, &usize)> = t.iter_ref().collect();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0], (&"apple".to_string(), &5));
+        assert_eq!(items[3], (&"pear".to_string(), &4));
+        assert_eq!((&t).into_iter().len(), 4);
+    }
+
+    #[test]
+    fn test_reuse_after_delete() {
+        let mut t: RbTree<i32, i32> = RbTree::new();
+        for round in 0..5 {
+            for i in 0..200 {
+                t.insert(i * 5 + round, i);
+            }
+            for i in (0..200).step_by(2) {
+                assert_eq!(t.remove_entry(&(i * 5 + round)), Some((i * 5 + round, i)));
+            }
+        }
+        assert_eq!(t.len(), 500);
+        let keys: Vec<i32> = t.iter_ref().map(|(k, _)| *k).collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let t = create_test_tree(100);
+        assert_send_sync(&t);
+
+        let sum: i32 = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|n| {
+                    let t = &t;
+                    s.spawn(move || (n..100).step_by(4).map(|k| t.get(&k).unwrap()).sum::<i32>())
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        assert_eq!(sum, 50);
+    }
+
+    #[test]
+    fn test_from_sorted_vec() {
+        for n in [0, 1, 2, 7, 8, 100, 1000] {
+            let tree = RbTree::from_sorted_vec((0..n).map(|i| (i, i * 2)).collect());
+            assert_eq!(tree.len(), n);
+            // perfectly balanced, so no deeper than log2(n + 1) rounded up
+            assert_eq!(
+                tree.height(),
+                (n as u32 + 1).next_power_of_two().ilog2() as usize
+            );
+            assert_eq!(
+                tree.get(&(n - 1)),
+                if n > 0 { Some((n - 1) * 2) } else { None }
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_if() {
+        let mut tree = create_test_tree(200);
+        let odd = tree.extract_if(|k, _| k % 2 == 1);
+        assert_eq!(odd.len(), 100);
+        assert!(odd.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(tree.len(), 100);
+        tree.retain(|k, _| *k < 100);
+        assert_eq!(tree.len(), 50);
+        tree.insert(1, 1).delete(0);
+        assert_eq!(
+            tree.iter_ref().map(|(k, _)| *k).take(3).collect::<Vec<_>>(),
+            vec![1, 2, 4]
+        );
+    }
+
+    #[test]
+    fn test_ceiling() {
+        let mut tree: RbTree<i32, i32> = RbTree::new();
+        assert_eq!(tree.ceiling(&0), None);
+        for k in (0..100).step_by(10) {
+            tree.insert(k, k);
+        }
+        assert_eq!(tree.ceiling(&-5), Some((&0, &0)));
+        assert_eq!(tree.ceiling(&40), Some((&40, &40)));
+        assert_eq!(tree.ceiling(&41), Some((&50, &50)));
+        assert_eq!(tree.ceiling(&91), None);
+    }
+
+    #[test]
+    fn test_pop_first() {
+        let mut tree: RbTree<u32, char> = RbTree::new();
+        for (k, v) in [(5, 'a'), (1, 'b'), (5, 'c'), (3, 'd'), (1, 'e')] {
+            tree.insert(k, v);
+        }
+        assert_eq!(tree.first(), Some((&1, &'b')));
+        let mut popped = Vec::new();
+        while let Some(entry) = tree.pop_first() {
+            popped.push(entry);
+        }
+        assert_eq!(
+            popped,
+            vec![(1, 'b'), (1, 'e'), (3, 'd'), (5, 'a'), (5, 'c')]
+        );
+        assert!(tree.first().is_none());
+        assert!(tree.is_empty());
     }
 }
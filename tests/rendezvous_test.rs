@@ -0,0 +1,74 @@
+use murmur_hash_rust::RendezvousHash;
+// Member every key from 0 to `keys` is routed to
+fn routes(hrw: &RendezvousHash<String>, keys: u64) -> Vec<String> {
+    (0..keys).map(|k| hrw.route(&k).unwrap().clone()).collect()
+}
+
+#[cfg(test)]
+mod rendezvous_test {
+    use murmur_hash_rust::RendezvousHash;
+
+    use crate::routes;
+
+    fn hrw(members: usize) -> RendezvousHash<String> {
+        let mut hrw = RendezvousHash::new(42);
+        for i in 0..members {
+            hrw.add(format!("node-{i}"));
+        }
+        hrw
+    }
+
+    #[test]
+    fn test_membership() {
+        let mut hrw: RendezvousHash<String> = RendezvousHash::new(42);
+        assert_eq!(hrw.route("key"), None);
+        assert!(hrw.add("a".to_string()));
+        assert!(!hrw.add_weighted("a".to_string(), 2.0));
+        assert!(hrw.add_weighted("b".to_string(), 2.0));
+        assert_eq!(hrw.weight(&"b".to_string()), Some(2.0));
+        assert_eq!(hrw.len(), 2);
+
+        let best = hrw.route_n("key", 5);
+        assert_eq!(best.len(), 2);
+        assert_eq!(Some(best[0]), hrw.route("key"));
+        assert!(hrw.remove(&"a".to_string()));
+        assert!(!hrw.contains(&"a".to_string()));
+    }
+
+    #[test]
+    fn test_balance_and_weights() {
+        let hrw = hrw(10);
+        let keys = routes(&hrw, 20000);
+        for node in hrw.members() {
+            let count = keys.iter().filter(|n| *n == node).count();
+            assert!((1800..2200).contains(&count), "{node} got {count}");
+        }
+
+        // a weight 3 member next to two weight 1 members gets 3/5 of the keys
+        let mut hrw = RendezvousHash::new(42);
+        hrw.add("a".to_string());
+        hrw.add("b".to_string());
+        hrw.add_weighted("heavy".to_string(), 3.0);
+        let heavy = routes(&hrw, 20000).iter().filter(|n| *n == "heavy").count();
+        assert!((11400..12600).contains(&heavy), "heavy got {heavy}");
+    }
+
+    #[test]
+    fn test_minimal_disruption() {
+        let mut hrw = hrw(10);
+        let before = routes(&hrw, 20000);
+
+        hrw.add("node-10".to_string());
+        let joined = routes(&hrw, 20000);
+        let moved = before.iter().zip(&joined).filter(|(a, b)| a != b);
+        assert!(moved.clone().all(|(_, b)| b == "node-10"));
+        assert!((1600..2000).contains(&moved.count()));
+
+        hrw.remove(&"node-10".to_string());
+        assert_eq!(routes(&hrw, 20000), before);
+        hrw.remove(&"node-3".to_string());
+        for (a, b) in before.iter().zip(routes(&hrw, 20000)) {
+            assert_eq!(*a == b, a != "node-3");
+        }
+    }
+}
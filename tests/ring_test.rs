@@ -0,0 +1,80 @@
+use murmur_hash_rust::HashRing;
+// Member every key from 0 to `keys` is routed to
+fn routes(ring: &HashRing<String>, keys: u64) -> Vec<String> {
+    (0..keys).map(|k| ring.route(&k).unwrap().clone()).collect()
+}
+
+#[cfg(test)]
+mod ring_test {
+    use murmur_hash_rust::HashRing;
+
+    use crate::routes;
+
+    fn ring(members: usize) -> HashRing<String> {
+        let mut ring = HashRing::new(100, 42);
+        for i in 0..members {
+            ring.add(format!("node-{i}"));
+        }
+        ring
+    }
+
+    #[test]
+    fn test_membership() {
+        let mut ring: HashRing<String> = HashRing::new(10, 42);
+        assert!(ring.is_empty());
+        assert_eq!(ring.route("key"), None);
+
+        assert!(ring.add("a".to_string()));
+        assert!(!ring.add("a".to_string()));
+        assert_eq!(ring.route("key"), Some(&"a".to_string()));
+        assert!(ring.add("b".to_string()));
+        assert_eq!(ring.members(), ["a".to_string(), "b".to_string()]);
+
+        assert!(ring.remove(&"a".to_string()));
+        assert!(!ring.remove(&"a".to_string()));
+        assert_eq!(ring.len(), 1);
+        assert!(routes(&ring, 100).iter().all(|n| n == "b"));
+    }
+
+    #[test]
+    fn test_balance() {
+        let ring = ring(10);
+        let keys = routes(&ring, 20000);
+        for node in ring.members() {
+            let count = keys.iter().filter(|n| *n == node).count();
+            // 2000 each if perfectly even
+            assert!((1400..2600).contains(&count), "{node} got {count}");
+        }
+    }
+
+    #[test]
+    fn test_add_moves_keys_to_new_member_only() {
+        let mut ring = ring(10);
+        let before = routes(&ring, 20000);
+        ring.add("node-10".to_string());
+        let after = routes(&ring, 20000);
+
+        let moved: Vec<_> = before.iter().zip(&after).filter(|(a, b)| a != b).collect();
+        assert!(moved.iter().all(|(_, b)| *b == "node-10"));
+        // about 1/11 of the keys
+        assert!((1200..2500).contains(&moved.len()), "{} moved", moved.len());
+    }
+
+    #[test]
+    fn test_remove_moves_only_its_keys() {
+        let mut ring = ring(10);
+        let before = routes(&ring, 20000);
+        ring.remove(&"node-3".to_string());
+        let after = routes(&ring, 20000);
+
+        for (a, b) in before.iter().zip(&after) {
+            assert_eq!(a == b, a != "node-3");
+        }
+        // the same members in any order route the same way
+        let mut shuffled = HashRing::new(100, 42);
+        for i in (0..10).rev().filter(|i| *i != 3) {
+            shuffled.add(format!("node-{i}"));
+        }
+        assert_eq!(routes(&shuffled, 20000), after);
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use murmur_hash_rust::Dict;
+// Random inserts, updates and deletes, replayed on a BTreeMap as the model
+fn churn<D: Dict<u64, u64>>(len: usize) -> (D, BTreeMap<u64, u64>) {
+    let mut d = D::new(len, 7);
+    let mut model = BTreeMap::new();
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    for round in 0..20_000 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let key = state % 3000;
+        if state.is_multiple_of(3) {
+            assert_eq!(d.pop(&key), model.remove(&key), "round {round}");
+        } else {
+            d.insert(key, round);
+            model.insert(key, round);
+        }
+    }
+    assert_eq!(d.len(), model.len());
+    for key in 0..3000 {
+        assert_eq!(d.get(&key), model.get(&key).copied(), "key {key}");
+    }
+    (d, model)
+}
+
+#[cfg(test)]
+mod robin_hood_test {
+    use murmur_hash_rust::{HashDict, RobinHoodDict};
+
+    use crate::churn;
+
+    #[test]
+    fn test_matches_model() {
+        let (d, model) = churn::<RobinHoodDict<u64, u64>>(16);
+        let entries: Vec<(u64, u64)> = d.iter().collect();
+        let expected: Vec<(u64, u64)> = model.into_iter().collect();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let (open, _) = churn::<RobinHoodDict<u64, u64>>(64);
+        let (chained, _) = churn::<HashDict<u64, u64>>(64);
+        assert_eq!(
+            open.iter().collect::<Vec<_>>(),
+            chained.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_grows() {
+        let mut d: RobinHoodDict<u32, u32> = RobinHoodDict::new(0, 42);
+        assert_eq!(d.capacity(), 8);
+        for i in 0..1000 {
+            d.insert(i, i);
+            assert!(d.len() * 8 <= d.capacity() * 7);
+        }
+        assert_eq!(d.capacity(), 2048);
+        assert!((0..1000).all(|i| d[&i] == i));
+
+        // sized up front, it never has to grow
+        let mut d: RobinHoodDict<u32, u32> = RobinHoodDict::new(1000, 42);
+        let capacity = d.capacity();
+        d.extend((0..1000).map(|i| (i, i)));
+        assert_eq!(d.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_backward_shift_delete() {
+        // a nearly full table has long runs, delete every other key and
+        // the rest must still be found with short probes
+        let mut d: RobinHoodDict<u32, u32> = RobinHoodDict::new(7000, 1);
+        let capacity = d.capacity();
+        for i in 0..7000 {
+            d.insert(i, i);
+        }
+        for i in (0..7000).step_by(2) {
+            assert_eq!(d.remove_entry(&i), Some((i, i)));
+        }
+        assert_eq!(d.capacity(), capacity);
+        assert_eq!(d.len(), 3500);
+        for i in 0..7000 {
+            assert_eq!(d.contains_key(&i), i % 2 == 1, "key {i}");
+        }
+        assert!(d.max_probe_len() < 32, "{}", d.max_probe_len());
+
+        for i in (1..7000).step_by(2) {
+            d.delete(&i);
+        }
+        assert!(d.is_empty());
+        assert_eq!(d.max_probe_len(), 0);
+    }
+
+    #[test]
+    fn test_borrowed_keys_and_update() {
+        let mut d: RobinHoodDict<String, u32> = RobinHoodDict::new(4, 42);
+        d.insert("apple".to_string(), 1)
+            .insert("pear".to_string(), 2);
+        d.update("apple", |v| *v += 10);
+        *d.get_mut("pear").unwrap() *= 3;
+        assert_eq!(d.get_key_value("apple"), Some((&"apple".to_string(), &11)));
+        assert_eq!(d["pear"], 6);
+        assert_eq!(d.pop("fig"), None);
+
+        let same: RobinHoodDict<String, u32> =
+            vec![("pear".to_string(), 6), ("apple".to_string(), 11)]
+                .into_iter()
+                .collect();
+        assert_eq!(d, same);
+        assert_eq!(format!("{:?}", d), r#"{"apple": 11, "pear": 6}"#);
+    }
+}
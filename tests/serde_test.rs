@@ -0,0 +1,86 @@
+#![cfg(feature = "serde-impls")]
+use murmur_hash_rust::HashDict;
+fn create_test_dict(size: i32) -> HashDict<String, i32> {
+    let mut d: HashDict<String, i32> = HashDict::new(97, 7);
+    for i in 0..size {
+        d.insert(format!("key{i}"), i);
+    }
+    d
+}
+
+#[cfg(test)]
+mod serde_test {
+    use murmur_hash_rust::{HashDict, RbTree, Tree};
+
+    use crate::create_test_dict;
+
+    #[test]
+    fn test_dict_json() {
+        let d = create_test_dict(200);
+        let json = serde_json::to_string(&d).unwrap();
+        let back: HashDict<String, i32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back, d);
+        assert_eq!(back.seed(), 7);
+        assert_eq!(back.bucket_count(), 97);
+        // same seed and bucket count means the same bucket layout
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn test_dict_bincode() {
+        let d = create_test_dict(200);
+        let bytes = bincode::serialize(&d).unwrap();
+        let back: HashDict<String, i32> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(back, d);
+        assert_eq!(bincode::serialize(&back).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_dict_msgpack() {
+        let d = create_test_dict(200);
+        let bytes = rmp_serde::to_vec(&d).unwrap();
+        let back: HashDict<String, i32> = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(back, d);
+        assert_eq!(back.seed(), d.seed());
+    }
+
+    #[test]
+    fn test_dict_zero_buckets() {
+        let json = r#"{"arr_length":0,"seed":1,"entries":[]}"#;
+        assert!(serde_json::from_str::<HashDict<String, i32>>(json).is_err());
+    }
+
+    #[test]
+    fn test_rb_tree_json() {
+        let mut t: RbTree<i32, String> = RbTree::new();
+        for i in [5, 3, 8, 1] {
+            t.insert(i, i.to_string());
+        }
+        let json = serde_json::to_string(&t).unwrap();
+        assert_eq!(json, r#"[[1,"1"],[3,"3"],[5,"5"],[8,"8"]]"#);
+
+        let back: RbTree<i32, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.len(), 4);
+        assert_eq!(back.get_ref(&8).map(String::as_str), Some("8"));
+    }
+
+    #[test]
+    fn test_tree_json() {
+        let mut t: Tree<i32> = Tree::new();
+        for i in [8, 4, 12, 2, 6] {
+            t.insert(i);
+        }
+        let json = serde_json::to_string(&t).unwrap();
+        assert_eq!(json, "[2,4,6,8,12]");
+
+        let back: Tree<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.to_string(), t.to_string());
+
+        let big: Tree<i32> =
+            serde_json::from_str(&serde_json::to_string(&(0..1023).collect::<Vec<i32>>()).unwrap())
+                .unwrap();
+        // sorted input is rebuilt balanced rather than as a list
+        assert_eq!(big.height(), 9);
+    }
+}
@@ -0,0 +1,109 @@
+use murmur_hash_rust::CountMinSketch;
+// Stream where key i shows up 1000 / (i + 1) times, with exact counts
+fn zipf(sketch: &mut CountMinSketch, keys: u64) -> Vec<u64> {
+    let counts: Vec<u64> = (0..keys).map(|i| 1000 / (i + 1)).collect();
+    for (i, &count) in counts.iter().enumerate() {
+        for _ in 0..count {
+            sketch.add(&(i as u64));
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod sketch_test {
+    use murmur_hash_rust::{CountMinSketch, SketchError, TopK};
+
+    use crate::zipf;
+
+    #[test]
+    fn test_estimates_within_bound() {
+        let mut sketch = CountMinSketch::new(0.01, 0.01, 42);
+        assert_eq!((sketch.width(), sketch.depth()), (272, 5));
+        let counts = zipf(&mut sketch, 2000);
+        assert_eq!(sketch.total(), counts.iter().sum::<u64>());
+
+        let bound = sketch.error_bound();
+        let mut over = 0;
+        for (i, &count) in counts.iter().enumerate() {
+            let estimate = sketch.estimate(&(i as u64));
+            assert!(estimate >= count);
+            if (estimate - count) as f64 > bound {
+                over += 1;
+            }
+        }
+        // allowed 1% of the time
+        assert!(over <= 20, "{over} estimates past the bound");
+    }
+
+    #[test]
+    fn test_conservative_update() {
+        let mut plain = CountMinSketch::with_dims(200, 4, 42);
+        let mut conservative = plain.clone();
+        conservative.set_conservative(true);
+        let counts = zipf(&mut plain, 2000);
+        zipf(&mut conservative, 2000);
+
+        let error = |sketch: &CountMinSketch| -> u64 {
+            (0..2000u64)
+                .map(|i| sketch.estimate(&i) - counts[i as usize])
+                .sum()
+        };
+        // a good deal less total overshoot
+        assert!(error(&conservative) * 4 < error(&plain) * 3);
+        assert!((0..2000u64).all(|i| conservative.estimate(&i) >= counts[i as usize]));
+        assert_eq!(conservative.add_n("new", 5), conservative.estimate("new"));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = CountMinSketch::with_dims(100, 4, 42);
+        let mut b = a.clone();
+        a.add_n("x", 3);
+        b.add_n("x", 4);
+        b.add("y");
+        a.merge(&b).unwrap();
+        assert!(a.estimate("x") >= 7);
+        assert_eq!(a.total(), 8);
+
+        let other = CountMinSketch::with_dims(100, 4, 7);
+        assert!(matches!(a.merge(&other), Err(SketchError::Incompatible)));
+        a.clear();
+        assert_eq!((a.total(), a.estimate("x")), (0, 0));
+    }
+
+    #[test]
+    fn test_counts_saturate() {
+        for conservative in [false, true] {
+            let mut a = CountMinSketch::with_dims(100, 4, 42);
+            a.set_conservative(conservative);
+            a.add_n("x", u64::MAX - 1);
+            assert_eq!(a.add_n("x", 5), u64::MAX);
+            assert_eq!(a.total(), u64::MAX);
+
+            let b = a.clone();
+            a.merge(&b).unwrap();
+            assert_eq!(a.estimate("x"), u64::MAX);
+            assert_eq!(a.total(), u64::MAX);
+        }
+    }
+
+    #[test]
+    fn test_top_k() {
+        let mut top: TopK<String> = TopK::new(5, 42);
+        // 200 one-off words between every round of the heavy hitters
+        for round in 0..50 {
+            for (w, n) in [("the", 10), ("of", 8), ("and", 6), ("to", 4), ("a", 2)] {
+                top.add_n(w.to_string(), n);
+            }
+            for i in 0..200 {
+                top.add(format!("rare-{round}-{i}"));
+            }
+        }
+        assert_eq!(top.len(), 5);
+        let words: Vec<&str> = top.top().iter().map(|(w, _)| w.as_str()).collect();
+        assert_eq!(words, ["the", "of", "and", "to", "a"]);
+        assert!(top.top()[0].1 >= 500);
+        assert!(top.estimate("missing") < 10);
+    }
+}
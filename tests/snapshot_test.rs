@@ -0,0 +1,124 @@
+use murmur_hash_rust::HashDict;
+fn create_test_dict(size: u32) -> HashDict<String, Vec<u32>> {
+    let mut d: HashDict<String, Vec<u32>> = HashDict::new(1009, 11);
+    for i in 0..size {
+        d.insert(format!("word{i}"), (0..i % 7).collect());
+    }
+    d
+}
+
+#[cfg(test)]
+mod snapshot_test {
+    use murmur_hash_rust::{HashDict, SnapshotError};
+
+    use crate::create_test_dict;
+
+    fn snapshot_bytes(d: &HashDict<String, Vec<u32>>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        d.write_snapshot(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn load(bytes: &[u8]) -> Result<HashDict<String, Vec<u32>>, SnapshotError> {
+        HashDict::read_snapshot(bytes)
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.snap");
+        // big enough to span several blocks
+        let d = create_test_dict(20_000);
+        d.save_to(&path).unwrap();
+
+        let back: HashDict<String, Vec<u32>> = HashDict::load_from(&path).unwrap();
+        assert_eq!(back, d);
+        assert_eq!(back.seed(), 11);
+        assert_eq!(back.bucket_count(), 1009);
+        assert!(!dir.path().join("words.snap.tmp").exists());
+    }
+
+    #[test]
+    fn test_empty_dict() {
+        let d: HashDict<String, Vec<u32>> = HashDict::new(3, 1);
+        let back = load(&snapshot_bytes(&d)).unwrap();
+        assert!(back.is_empty());
+        assert_eq!(back.bucket_count(), 3);
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        let mut bytes = snapshot_bytes(&create_test_dict(10));
+        bytes[0] = b'X';
+        assert!(matches!(load(&bytes), Err(SnapshotError::BadMagic)));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut bytes = snapshot_bytes(&create_test_dict(10));
+        bytes[4] = 99;
+        assert!(matches!(
+            load(&bytes),
+            Err(SnapshotError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_header() {
+        let mut bytes = snapshot_bytes(&create_test_dict(10));
+        // flip a bit in the seed
+        bytes[8] ^= 1;
+        assert!(matches!(load(&bytes), Err(SnapshotError::CorruptHeader)));
+    }
+
+    #[test]
+    fn test_corrupt_block() {
+        let mut bytes = snapshot_bytes(&create_test_dict(10));
+        let last = bytes.len() - 20;
+        bytes[last] ^= 0x40;
+        assert!(matches!(
+            load(&bytes),
+            Err(SnapshotError::ChecksumMismatch { block: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = snapshot_bytes(&create_test_dict(20_000));
+        for cut in [0, 10, 36, 40, 1000, bytes.len() / 2, bytes.len() - 1] {
+            assert!(
+                matches!(load(&bytes[..cut]), Err(SnapshotError::Truncated)),
+                "cut at {cut}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut bytes = snapshot_bytes(&create_test_dict(10));
+        bytes.push(0);
+        assert!(matches!(load(&bytes), Err(SnapshotError::Corrupt(_))));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let res: Result<HashDict<String, u32>, _> = HashDict::load_from(dir.path().join("nope"));
+        assert!(matches!(res, Err(SnapshotError::Io(_))));
+    }
+
+    #[test]
+    fn test_failed_save_removes_tmp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // renaming a file over a directory fails after the tmp file exists
+        let path = dir.path().join("taken");
+        std::fs::create_dir(&path).unwrap();
+
+        assert!(matches!(
+            create_test_dict(10).save_to(&path),
+            Err(SnapshotError::Io(_))
+        ));
+        assert!(!dir.path().join("taken.tmp").exists());
+        assert!(path.is_dir());
+    }
+}
@@ -0,0 +1,121 @@
+use murmur_hash_rust::HashDict;
+// A very large table holding only a handful of keys
+fn sparse_dict(keys: u32) -> HashDict<u32, u32> {
+    let mut d = HashDict::new(1 << 24, 42);
+    for i in 0..keys {
+        d.insert(i, i * 3);
+    }
+    d
+}
+
+#[cfg(test)]
+mod sparse_test {
+    use murmur_hash_rust::{HashDict, HybridBucket, SmallBucket};
+
+    use crate::sparse_dict;
+
+    #[test]
+    fn test_huge_sparse_table() {
+        let mut d = sparse_dict(10);
+        assert_eq!(d.bucket_count(), 1 << 24);
+        assert_eq!(d.len(), 10);
+        assert!((0..10).all(|i| d.get(&i) == Some(i * 3)));
+        assert_eq!(d.get(&10), None);
+        assert_eq!(d.pop(&3), Some(9));
+        assert!(!d.contains_key(&3));
+
+        let stats = d.stats();
+        assert_eq!(stats.entries, 9);
+        assert_eq!(stats.empty_buckets, (1 << 24) - 9);
+    }
+
+    #[test]
+    fn test_groups_stay_bounded() {
+        // the keys land all over a table of 2^40 buckets, a group directory
+        // reaching up to the highest one would take terabytes
+        let mut d: HashDict<u32, u32> = HashDict::new(1 << 40, 42);
+        for i in 0..100 {
+            d.insert(i, i);
+        }
+        assert!((0..100).all(|i| d.get(&i) == Some(i)));
+        assert_eq!(d.stats().bucket_groups, 100);
+
+        let mut d = sparse_dict(1000);
+        assert!(d.stats().bucket_groups <= 1000);
+        d.retain(|k, _| k % 2 == 0);
+        assert!(d.stats().bucket_groups <= 500);
+        for i in 0..1000 {
+            d.delete(&i);
+        }
+        assert_eq!(d.stats().bucket_groups, 0);
+    }
+
+    #[test]
+    fn test_fill_large_table() {
+        // enough keys to allocate most of the 2^18 groups, one by one in
+        // random order, which is quadratic if adding a group shifts the rest
+        let mut d = sparse_dict(300_000);
+        assert_eq!(d.len(), 300_000);
+        let groups = d.stats().bucket_groups;
+        assert!(groups > 150_000 && groups <= 1 << 18, "{groups} groups");
+        assert!((0..300_000).step_by(7).all(|i| d.get(&i) == Some(i * 3)));
+
+        d.retain(|k, _| k % 3 == 0);
+        assert_eq!(d.len(), 100_000);
+        assert!(d.stats().bucket_groups < groups);
+        assert_eq!(d.iter().count(), 100_000);
+    }
+
+    #[test]
+    fn test_emptied_buckets_are_released() {
+        let mut d = sparse_dict(1000);
+        for i in 0..1000 {
+            d.delete(&i);
+        }
+        assert!(d.is_empty());
+        assert_eq!(d.stats().empty_buckets, 1 << 24);
+        d.shrink_to_fit();
+        d.insert(7, 7);
+        assert_eq!(d.get(&7), Some(7));
+    }
+
+    #[test]
+    fn test_dense_table_every_bucket_kind() {
+        // far more keys than buckets, so every group fills up
+        let mut trees: HashDict<u32, u32> = HashDict::new(100, 1);
+        let mut lists: HashDict<u32, u32, SmallBucket<_, _>> = HashDict::with_bucket_type(100, 1);
+        let mut hybrids: HashDict<u32, u32, HybridBucket<_, _>> =
+            HashDict::with_bucket_type(100, 1);
+        for i in 0..5000 {
+            trees.insert(i, i);
+            lists.insert(i, i);
+            hybrids.insert(i, i);
+        }
+        trees.retain(|k, _| k % 7 != 0);
+        lists.retain(|k, _| k % 7 != 0);
+        hybrids.retain(|k, _| k % 7 != 0);
+        assert_eq!(trees.stats().empty_buckets, 0);
+        assert_eq!(
+            trees.iter().collect::<Vec<_>>(),
+            lists.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            trees.iter().collect::<Vec<_>>(),
+            hybrids.iter().collect::<Vec<_>>()
+        );
+
+        trees.clear();
+        assert_eq!(trees.stats().empty_buckets, 100);
+    }
+
+    #[test]
+    fn test_bucket_csv_lists_empty_buckets() {
+        let mut d: HashDict<u32, u32> = HashDict::new(200, 3);
+        d.insert(1, 1);
+        let mut csv = Vec::new();
+        d.write_bucket_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 201);
+        assert_eq!(csv.lines().filter(|l| l.ends_with(",1")).count(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+use murmur_hash_rust::HashDict;
+fn create_test_dict(size: u32, buckets: usize) -> HashDict<u32, u32> {
+    let mut d = HashDict::new(buckets, 42);
+    for i in 0..size {
+        d.insert(i, i);
+    }
+    d
+}
+
+#[cfg(test)]
+mod stats_test {
+    use murmur_hash_rust::{HashDict, RbTree};
+
+    use crate::create_test_dict;
+
+    #[test]
+    fn test_stats() {
+        let d = create_test_dict(5000, 1000);
+        let s = d.stats();
+        assert_eq!(s.entries, 5000);
+        assert_eq!(s.buckets, 1000);
+        assert_eq!(s.load_factor, 5.0);
+        assert_eq!(s.histogram.iter().sum::<usize>(), 1000);
+        let counted: usize = s.histogram.iter().enumerate().map(|(n, c)| n * c).sum();
+        assert_eq!(counted, 5000);
+        assert_eq!(s.empty_buckets, s.histogram[0]);
+        assert_eq!(s.max_bucket_len, s.histogram.len() - 1);
+        assert!(s.max_tree_height >= 1);
+
+        // murmur3 should be close to a uniform spread
+        assert!((s.observed_collision_rate - s.expected_collision_rate).abs() < 0.02);
+        assert!(format!("{s}").contains("load factor:      5.000"));
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let d: HashDict<u32, u32> = HashDict::new(16, 1);
+        let s = d.stats();
+        assert_eq!(s.empty_buckets, 16);
+        assert_eq!(s.histogram, vec![16]);
+        assert_eq!(s.max_tree_height, 0);
+        assert_eq!(s.observed_collision_rate, 0.0);
+        assert_eq!(s.expected_collision_rate, 0.0);
+    }
+
+    #[test]
+    fn test_single_bucket() {
+        let d = create_test_dict(100, 1);
+        let s = d.stats();
+        assert_eq!(s.max_bucket_len, 100);
+        assert_eq!(s.observed_collision_rate, 0.99);
+        assert!((s.expected_collision_rate - 0.99).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tree_height() {
+        let mut t: RbTree<u32, u32> = RbTree::new();
+        assert_eq!(t.height(), 0);
+        for i in 0..1023 {
+            t.insert(i, i);
+        }
+        // a red-black tree is at most 2 * log2(n + 1) high
+        assert!(t.height() >= 10 && t.height() <= 20);
+    }
+
+    #[test]
+    fn test_bucket_csv() {
+        let d = create_test_dict(30, 4);
+        let mut out = Vec::new();
+        d.write_bucket_csv(&mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "bucket,size");
+        assert_eq!(lines.len(), 5);
+        let total: usize = lines[1..]
+            .iter()
+            .map(|l| l.split(',').nth(1).unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, 30);
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use murmur_hash_rust::{ManualClock, TtlDict};
+// A dict on a manual clock, with the clock handed back to move time along
+fn frozen_dict() -> (TtlDict<String, u32, ManualClock>, ManualClock) {
+    let clock = ManualClock::new();
+    (TtlDict::with_clock(64, 42, clock.clone()), clock)
+}
+
+fn secs(n: u64) -> Duration {
+    Duration::from_secs(n)
+}
+
+#[cfg(test)]
+mod ttl_test {
+    use std::time::Duration;
+
+    use crate::{frozen_dict, secs};
+
+    #[test]
+    fn test_expired_entries_are_invisible() {
+        let (mut d, clock) = frozen_dict();
+        d.insert_with_ttl("short".to_string(), 1, secs(10))
+            .insert_with_ttl("long".to_string(), 2, secs(60))
+            .insert("forever".to_string(), 3);
+        assert_eq!(d.get("short"), Some(1));
+        assert_eq!(d.ttl("short"), Some(secs(10)));
+        assert_eq!(d.ttl("forever"), None);
+
+        clock.advance(secs(10));
+        assert_eq!(d.get("short"), None);
+        assert!(!d.contains_key("short"));
+        assert_eq!(d.get_mut("long").copied(), Some(2));
+        assert_eq!(d.ttl("long"), Some(secs(50)));
+
+        clock.advance(secs(1000));
+        assert_eq!(d.get("long"), None);
+        assert_eq!(d.get("forever"), Some(3));
+        // still stored until purged
+        assert_eq!(d.len(), 3);
+    }
+
+    #[test]
+    fn test_purge_expired() {
+        let (mut d, clock) = frozen_dict();
+        for i in 0..100u32 {
+            d.insert_with_ttl(i.to_string(), i, secs(u64::from(i) + 1));
+        }
+        d.insert("keep".to_string(), 0);
+
+        assert_eq!(d.purge_expired(), 0);
+        clock.advance(secs(40));
+        assert_eq!(d.purge_expired(), 40);
+        assert_eq!(d.len(), 61);
+        assert_eq!(d.get("40"), Some(40));
+        clock.advance(secs(1000));
+        assert_eq!(d.purge_expired(), 60);
+        assert_eq!(d.len(), 1);
+    }
+
+    #[test]
+    fn test_overwrite_resets_deadline() {
+        let (mut d, clock) = frozen_dict();
+        d.insert_with_ttl("a".to_string(), 1, secs(5));
+        d.insert_with_ttl("b".to_string(), 1, secs(5));
+        d.insert_with_ttl("a".to_string(), 2, secs(30));
+        d.insert("b".to_string(), 2);
+
+        // the old deadlines were replaced and must not remove the new entries
+        clock.advance(secs(10));
+        assert_eq!(d.purge_expired(), 0);
+        assert_eq!(d.get("a"), Some(2));
+        assert_eq!(d.get("b"), Some(2));
+
+        clock.advance(secs(20));
+        assert_eq!(d.pop("a"), None);
+        assert_eq!(d.purge_expired(), 0);
+        assert_eq!(d.len(), 1);
+    }
+
+    #[test]
+    fn test_ttl_past_the_clock_never_expires() {
+        let (mut d, clock) = frozen_dict();
+        d.insert_with_ttl("a".to_string(), 1, Duration::MAX);
+        assert_eq!(d.ttl("a"), None);
+        clock.advance(secs(1_000_000));
+        assert_eq!(d.purge_expired(), 0);
+        assert_eq!(d.get("a"), Some(1));
+
+        // replacing it with a real deadline still expires
+        d.insert_with_ttl("a".to_string(), 2, secs(1));
+        clock.advance(secs(1));
+        assert_eq!(d.purge_expired(), 1);
+        assert!(d.is_empty());
+    }
+}
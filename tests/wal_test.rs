@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use murmur_hash_rust::{DurableHashDict, FsyncPolicy};
+fn open_test_dict(dir: &Path, fsync: FsyncPolicy) -> DurableHashDict<String, u32> {
+    DurableHashDict::open(dir, 101, 7, fsync).unwrap()
+}
+
+#[cfg(test)]
+mod wal_test {
+    use std::fs::{self, OpenOptions};
+
+    use murmur_hash_rust::{hash_mumur3, DurableHashDict, FsyncPolicy, WalError};
+
+    use crate::open_test_dict;
+
+    #[test]
+    fn test_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+            for i in 0..100 {
+                d.insert(format!("key{i}"), i).unwrap();
+            }
+            d.insert("key5".to_string(), 500).unwrap();
+            d.delete("key7").unwrap();
+            assert_eq!(d.pop("key8").unwrap(), Some(8));
+            assert_eq!(d.pop("missing").unwrap(), None);
+        }
+
+        let d = open_test_dict(dir.path(), FsyncPolicy::Always);
+        assert_eq!(d.len(), 98);
+        assert_eq!(d.get("key5"), Some(500));
+        assert_eq!(d.get("key99"), Some(99));
+        assert!(!d.contains_key("key7"));
+        assert!(!d.contains_key("key8"));
+        assert_eq!(d.dict().seed(), 7);
+    }
+
+    #[test]
+    fn test_fsync_policies() {
+        for policy in [
+            FsyncPolicy::Always,
+            FsyncPolicy::Batched(16),
+            FsyncPolicy::Never,
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            {
+                let mut d = open_test_dict(dir.path(), policy);
+                for i in 0..50 {
+                    d.insert(format!("key{i}"), i).unwrap();
+                }
+            }
+            let d = open_test_dict(dir.path(), policy);
+            assert_eq!(d.len(), 50, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("wal");
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Never);
+            d.set_compact_after(Some(100));
+            for i in 0..250 {
+                d.insert(format!("key{i}"), i).unwrap();
+            }
+            d.sync().unwrap();
+            assert!(dir.path().join("snapshot").exists());
+            // 50 records since the second compaction
+            let len = fs::metadata(&wal).unwrap().len();
+            d.compact().unwrap();
+            assert!(fs::metadata(&wal).unwrap().len() < len);
+            d.delete("key0").unwrap();
+        }
+
+        let d = open_test_dict(dir.path(), FsyncPolicy::Never);
+        assert_eq!(d.len(), 249);
+        assert_eq!(d.get("key249"), Some(249));
+        assert!(!d.contains_key("key0"));
+    }
+
+    #[test]
+    fn test_torn_tail_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("wal");
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+            for i in 0..10 {
+                d.insert(format!("key{i}"), i).unwrap();
+            }
+        }
+        let len = fs::metadata(&wal).unwrap().len();
+        // cut the last record in half
+        OpenOptions::new()
+            .write(true)
+            .open(&wal)
+            .unwrap()
+            .set_len(len - 5)
+            .unwrap();
+
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+            assert_eq!(d.len(), 9);
+            assert!(!d.contains_key("key9"));
+            d.insert("after".to_string(), 1).unwrap();
+        }
+        let d = open_test_dict(dir.path(), FsyncPolicy::Always);
+        assert_eq!(d.len(), 10);
+        assert_eq!(d.get("after"), Some(1));
+    }
+
+    #[test]
+    fn test_torn_checksum_at_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("wal");
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+            d.insert("a".to_string(), 1).unwrap();
+            d.insert("b".to_string(), 2).unwrap();
+        }
+        let mut bytes = fs::read(&wal).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&wal, &bytes).unwrap();
+
+        let d = open_test_dict(dir.path(), FsyncPolicy::Always);
+        assert_eq!(d.len(), 1);
+        // both records are the same size, only the header and the first are left
+        let kept = 8 + (bytes.len() - 8) / 2;
+        assert_eq!(fs::metadata(&wal).unwrap().len(), kept as u64);
+    }
+
+    #[test]
+    fn test_corrupt_middle_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("wal");
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+            d.insert("a".to_string(), 1).unwrap();
+            d.insert("b".to_string(), 2).unwrap();
+        }
+        let mut bytes = fs::read(&wal).unwrap();
+        // last byte of the first record's value
+        bytes[8 + 12 + 13] ^= 0xff;
+        fs::write(&wal, &bytes).unwrap();
+
+        let res = DurableHashDict::<String, u32>::open(dir.path(), 101, 7, FsyncPolicy::Always);
+        assert!(matches!(res, Err(WalError::Corrupt { offset: 8 })));
+    }
+
+    #[test]
+    fn test_corrupt_middle_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = dir.path().join("wal");
+        {
+            let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+            for key in ["a", "b", "c"] {
+                d.insert(key.to_string(), 1).unwrap();
+            }
+        }
+        let mut bytes = fs::read(&wal).unwrap();
+        // every record is 26 bytes, make the second one claim to run far
+        // past the end of the log
+        bytes[8 + 26 + 3] = 0x7f;
+        fs::write(&wal, &bytes).unwrap();
+
+        let res = DurableHashDict::<String, u32>::open(dir.path(), 101, 7, FsyncPolicy::Always);
+        assert!(matches!(res, Err(WalError::Corrupt { offset: 34 })));
+        // nothing was truncated
+        assert_eq!(fs::read(&wal).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_failed_compaction_keeps_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut d = open_test_dict(dir.path(), FsyncPolicy::Always);
+        d.set_compact_after(Some(2));
+        // a directory where the snapshot goes makes the rename fail
+        let snapshot = dir.path().join("snapshot");
+        fs::create_dir(&snapshot).unwrap();
+
+        d.insert("a".to_string(), 1).unwrap();
+        d.insert("b".to_string(), 2).unwrap();
+        assert_eq!(d.get("b"), Some(2));
+        assert!(matches!(
+            d.take_compact_error(),
+            Some(WalError::Snapshot(_))
+        ));
+        assert!(d.take_compact_error().is_none());
+
+        // retried two records later
+        fs::remove_dir(&snapshot).unwrap();
+        d.insert("c".to_string(), 3).unwrap();
+        assert!(fs::metadata(dir.path().join("wal")).unwrap().len() > 8);
+        d.insert("d".to_string(), 4).unwrap();
+        assert!(d.take_compact_error().is_none());
+        assert_eq!(fs::metadata(dir.path().join("wal")).unwrap().len(), 8);
+        drop(d);
+
+        let d = open_test_dict(dir.path(), FsyncPolicy::Always);
+        assert_eq!(d.len(), 4);
+    }
+
+    #[test]
+    fn test_hand_written_record() {
+        // insert "x" => 5, checksummed as murmur3 over length and payload
+        let mut payload = vec![1u8];
+        payload.extend_from_slice(&1u64.to_le_bytes());
+        payload.extend_from_slice(b"x");
+        payload.extend_from_slice(&5u32.to_le_bytes());
+        let len = (payload.len() as u32).to_le_bytes();
+        let framed = [&len[..], &payload].concat();
+        let sum = hash_mumur3(&framed, 0x4d4d574c).unwrap() as u64;
+
+        let mut log = b"MMWL\x01\x00\x00\x00".to_vec();
+        log.extend_from_slice(&len);
+        log.extend_from_slice(&sum.to_le_bytes());
+        log.extend_from_slice(&payload);
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("wal"), &log).unwrap();
+
+        let d = open_test_dict(dir.path(), FsyncPolicy::Always);
+        assert_eq!(d.get("x"), Some(5));
+    }
+
+    #[test]
+    fn test_bad_log_header() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("wal"), b"NOPE\x01\x00\x00\x00").unwrap();
+        let res = DurableHashDict::<String, u32>::open(dir.path(), 101, 7, FsyncPolicy::Always);
+        assert!(matches!(res, Err(WalError::BadMagic)));
+
+        fs::write(dir.path().join("wal"), b"MMWL\x02\x00\x00\x00").unwrap();
+        let res = DurableHashDict::<String, u32>::open(dir.path(), 101, 7, FsyncPolicy::Always);
+        assert!(matches!(res, Err(WalError::UnsupportedVersion(2))));
+    }
+}